@@ -1,40 +1,370 @@
 use crate::CustomError;
+use crate::loader::{FormatType, LoadOptions};
 use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
 use polars::prelude::*;
-use polars_plan::plans::{DynLiteralValue, LiteralValue};
+use polars_plan::plans::{
+    DynLiteralValue, LiteralValue,
+    visitor::{RewriteRecursion, RewritingVisitor, TreeWalker},
+};
 use sqlparser::{
     ast::{
-        BinaryOperator as SqlBinaryOperator, Expr as SqlExpr, Function, FunctionArg,
-        FunctionArgExpr, FunctionArgumentList, FunctionArguments, GroupByExpr, Ident, LimitClause,
-        ObjectNamePart, Offset as SqlOffset, OrderBy, OrderByKind, Select, SelectItem, SetExpr,
-        Statement, TableFactor, TableWithJoins, Value as SqlValue, ValueWithSpan,
+        BinaryOperator as SqlBinaryOperator, CastKind, CeilFloorKind, DataType as SqlDataType,
+        DateTimeField, Distinct as SqlDistinct, DuplicateTreatment, Expr as SqlExpr, Function,
+        FunctionArg, FunctionArgExpr, FunctionArgumentList, FunctionArguments, GroupByExpr, Ident,
+        Interval, Join as SqlJoin, JoinConstraint, JoinOperator, LimitClause, ObjectName,
+        ObjectNamePart, Offset as SqlOffset, OrderBy, OrderByKind, Query, Select, SelectItem,
+        SetExpr, SetOperator, SetQuantifier, Statement, TableFactor, TableFunctionArgs,
+        TableWithJoins, TimezoneInfo, TrimWhereField, UnaryOperator, Value as SqlValue,
+        ValueWithSpan, WindowFrameBound, WindowFrameUnits, WindowSpec, WindowType, With,
     },
     tokenizer::Token,
 };
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Custom Sql struct
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sql<'a> {
     pub(crate) aggregation: Vec<Expr>,
+    pub(crate) window: Vec<Expr>,
     pub(crate) selection: Vec<Expr>,
     pub(crate) condition: Option<Expr>,
-    pub(crate) source: &'a str,
-    pub(crate) order_by: Vec<(&'a str, OrderType)>,
-    pub(crate) group_by: Vec<&'a str>,
+    pub(crate) having: Option<Expr>,
+    pub(crate) source: Source<'a>,
+    pub(crate) order_by: Vec<(Expr, OrderType, NullsOrder)>,
+    pub(crate) group_by: Vec<Expr>,
     pub(crate) offset: Option<i64>,
     pub(crate) limit: Option<usize>,
+    pub(crate) distinct: Option<Distinct<'a>>,
+    pub(crate) subqueries: Vec<Subquery<'a>>,
+    pub(crate) ctes: Vec<Cte<'a>>,
+}
+
+/// Relation of the from clause, a single table, a table read by function with options,
+/// a derived table, the set operation of two queries, the join of two relations or
+/// a relation renamed by alias
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source<'a> {
+    Table(&'a str),
+    Read {
+        path: &'a str,
+        format_type: FormatType,
+        options: LoadOptions,
+    },
+    Query(Box<Sql<'a>>),
+    Alias(Box<Source<'a>>, &'a str),
+    Set {
+        left: Box<Sql<'a>>,
+        right: Box<Sql<'a>>,
+        op: SetOperation,
+        all: bool,
+    },
+    Join {
+        left: Box<Source<'a>>,
+        right: Box<Source<'a>>,
+        how: JoinType,
+        on: JoinOn<'a>,
+    },
+}
+
+/// Join condition, example: on a = b, using (a, b)
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinOn<'a> {
+    Expr(Expr),
+    Using(Vec<&'a str>),
+    Natural,
+    None,
+}
+
+/// Distinct of select, example: select distinct a, b; select distinct on (a) a, b
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distinct<'a> {
+    All,
+    On(Vec<&'a str>),
+}
+
+/// Set operation of two queries, example: union, intersect, except
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl std::fmt::Display for SetOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetOperation::Union => write!(f, "UNION"),
+            SetOperation::Intersect => write!(f, "INTERSECT"),
+            SetOperation::Except => write!(f, "EXCEPT"),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Resolution of column names, names match exactly by default and a case insensitive
+/// match is used when there is no exact one, example: sepallength is sepalLength
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Resolution {
+    #[default]
+    CaseSensitive,
+    CaseInsensitive,
+}
+
+/// Uncorrelated subquery of expression, it is referenced by a placeholder column
+/// and replaced with its result before the outer query runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subquery<'a> {
+    pub(crate) name: String,
+    pub(crate) kind: SubqueryKind,
+    pub(crate) sql: Sql<'a>,
+}
+
+/// Usage of subquery, example: (select max(a) from t), a in (select b from t), exists (select * from t)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubqueryKind {
+    Scalar,
+    List,
+    Exists,
+}
+
+/// Common table expression, example: with t (a, b) as (select c, d from source)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cte<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) columns: Vec<&'a str>,
+    pub(crate) sql: Sql<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AggFunc {
     Max,
     Min,
     Sum,
     Avg,
     Count,
+    Median,
+    StddevSamp,
+    StddevPop,
+    VarSamp,
+    VarPop,
+    PercentileCont,
+    ApproxCountDistinct,
+    First,
+    Last,
+    ArrayAgg,
+    StringAgg,
+}
+
+impl AggFunc {
+    /// Number of arguments, example: string_agg(name, ',')
+    fn arity(&self) -> usize {
+        match self {
+            Self::StringAgg => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Scalar function computed on each row, example: coalesce(a, b, 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarFunc {
+    Coalesce,
+    NullIf,
+    IfNull,
+    If,
+    Greatest,
+    Least,
+    Lower,
+    Upper,
+    Ltrim,
+    Rtrim,
+    Length,
+    Substr,
+    Replace,
+    Concat,
+    SplitPart,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Lpad,
+    Rpad,
+    RegexpReplace,
+    RegexpExtract,
+    RegexpLike,
+    DateTrunc,
+    DatePart,
+    Strftime,
+    Strptime,
+    ToDate,
+    ToTimestamp,
+    Now,
+    CurrentDate,
+    DateAdd,
+    DateSub,
+    DateDiff,
+    Epoch,
+    Abs,
+    Round,
+    Ceil,
+    Floor,
+    Sqrt,
+    Cbrt,
+    Power,
+    Ln,
+    Log,
+    Log2,
+    Log10,
+    Exp,
+    Sign,
+    Sin,
+    Cos,
+    Tan,
+    Cot,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Degrees,
+    Radians,
+    Pi,
+    Mod,
+    Div,
+}
+
+impl ScalarFunc {
+    /// Minimum and maximum number of arguments
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Self::Coalesce | Self::Greatest | Self::Least | Self::Concat => (1, usize::MAX),
+            Self::Now | Self::CurrentDate | Self::Pi => (0, 0),
+            Self::Lower
+            | Self::Upper
+            | Self::Length
+            | Self::Epoch
+            | Self::Abs
+            | Self::Ceil
+            | Self::Floor
+            | Self::Sqrt
+            | Self::Cbrt
+            | Self::Ln
+            | Self::Log2
+            | Self::Log10
+            | Self::Exp
+            | Self::Sign
+            | Self::Sin
+            | Self::Cos
+            | Self::Tan
+            | Self::Cot
+            | Self::Asin
+            | Self::Acos
+            | Self::Atan
+            | Self::Degrees
+            | Self::Radians => (1, 1),
+            Self::Ltrim
+            | Self::Rtrim
+            | Self::ToDate
+            | Self::ToTimestamp
+            | Self::Round
+            | Self::Log => (1, 2),
+            Self::NullIf
+            | Self::IfNull
+            | Self::StartsWith
+            | Self::EndsWith
+            | Self::Contains
+            | Self::RegexpLike
+            | Self::DateTrunc
+            | Self::DatePart
+            | Self::Strftime
+            | Self::Strptime
+            | Self::DateAdd
+            | Self::DateSub
+            | Self::Power
+            | Self::Atan2
+            | Self::Mod
+            | Self::Div => (2, 2),
+            Self::Substr | Self::Lpad | Self::Rpad | Self::RegexpExtract => (2, 3),
+            Self::If | Self::Replace | Self::SplitPart | Self::DateDiff => (3, 3),
+            Self::RegexpReplace => (3, 4),
+        }
+    }
+}
+
+impl TryFrom<&str> for ScalarFunc {
+    type Error = CustomError;
+
+    fn try_from(s: &str) -> std::result::Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "coalesce" => Ok(Self::Coalesce),
+            "nullif" => Ok(Self::NullIf),
+            "ifnull" | "nvl" => Ok(Self::IfNull),
+            "if" | "iif" => Ok(Self::If),
+            "greatest" => Ok(Self::Greatest),
+            "least" => Ok(Self::Least),
+            "lower" | "lcase" => Ok(Self::Lower),
+            "upper" | "ucase" => Ok(Self::Upper),
+            "ltrim" => Ok(Self::Ltrim),
+            "rtrim" => Ok(Self::Rtrim),
+            "length" | "char_length" | "character_length" => Ok(Self::Length),
+            "substr" | "substring" => Ok(Self::Substr),
+            "replace" => Ok(Self::Replace),
+            "concat" => Ok(Self::Concat),
+            "split_part" => Ok(Self::SplitPart),
+            "starts_with" => Ok(Self::StartsWith),
+            "ends_with" => Ok(Self::EndsWith),
+            "contains" => Ok(Self::Contains),
+            "lpad" => Ok(Self::Lpad),
+            "rpad" => Ok(Self::Rpad),
+            "regexp_replace" => Ok(Self::RegexpReplace),
+            "regexp_extract" => Ok(Self::RegexpExtract),
+            "regexp_like" | "regexp_matches" => Ok(Self::RegexpLike),
+            "date_trunc" => Ok(Self::DateTrunc),
+            "date_part" => Ok(Self::DatePart),
+            "strftime" | "to_char" => Ok(Self::Strftime),
+            "strptime" => Ok(Self::Strptime),
+            "to_date" => Ok(Self::ToDate),
+            "to_timestamp" => Ok(Self::ToTimestamp),
+            "now" | "current_timestamp" | "localtimestamp" => Ok(Self::Now),
+            "current_date" => Ok(Self::CurrentDate),
+            "date_add" => Ok(Self::DateAdd),
+            "date_sub" => Ok(Self::DateSub),
+            "date_diff" | "datediff" => Ok(Self::DateDiff),
+            "epoch" => Ok(Self::Epoch),
+            "abs" => Ok(Self::Abs),
+            "round" => Ok(Self::Round),
+            "ceil" | "ceiling" => Ok(Self::Ceil),
+            "floor" => Ok(Self::Floor),
+            "sqrt" => Ok(Self::Sqrt),
+            "cbrt" => Ok(Self::Cbrt),
+            "power" | "pow" => Ok(Self::Power),
+            "ln" => Ok(Self::Ln),
+            "log" => Ok(Self::Log),
+            "log2" => Ok(Self::Log2),
+            "log10" => Ok(Self::Log10),
+            "exp" => Ok(Self::Exp),
+            "sign" | "signum" => Ok(Self::Sign),
+            "sin" => Ok(Self::Sin),
+            "cos" => Ok(Self::Cos),
+            "tan" => Ok(Self::Tan),
+            "cot" => Ok(Self::Cot),
+            "asin" => Ok(Self::Asin),
+            "acos" => Ok(Self::Acos),
+            "atan" => Ok(Self::Atan),
+            "atan2" => Ok(Self::Atan2),
+            "degrees" => Ok(Self::Degrees),
+            "radians" => Ok(Self::Radians),
+            "pi" => Ok(Self::Pi),
+            "mod" => Ok(Self::Mod),
+            "div" => Ok(Self::Div),
+            _ => Err(CustomError::SqlExprFuncItem(format!(
+                "Unsupported scalar function: {}",
+                s
+            ))),
+        }
+    }
 }
 
 impl AggFunc {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "max" => Some(Self::Max),
@@ -42,6 +372,17 @@ impl AggFunc {
             "sum" => Some(Self::Sum),
             "avg" => Some(Self::Avg),
             "count" => Some(Self::Count),
+            "median" => Some(Self::Median),
+            "stddev" | "stddev_samp" => Some(Self::StddevSamp),
+            "stddev_pop" => Some(Self::StddevPop),
+            "variance" | "var_samp" => Some(Self::VarSamp),
+            "var_pop" => Some(Self::VarPop),
+            "percentile_cont" => Some(Self::PercentileCont),
+            "approx_count_distinct" => Some(Self::ApproxCountDistinct),
+            "first" => Some(Self::First),
+            "last" => Some(Self::Last),
+            "array_agg" => Some(Self::ArrayAgg),
+            "string_agg" => Some(Self::StringAgg),
             _ => None,
         }
     }
@@ -53,22 +394,130 @@ pub(crate) enum OrderType {
     Desc,
 }
 
-pub struct InterimExpr(pub(crate) Box<SqlExpr>);
+// Position of nulls in an ordering, defaults to last for ascending and first for descending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NullsOrder {
+    First,
+    Last,
+}
+
+// Query body, example: select a from t where b > 1
+pub struct InterimQuery<'a>(pub(crate) &'a Query);
+// Query body and select, example: select a from t union select b from s
+pub struct InterimSetExpr<'a>(pub(crate) &'a SetExpr);
+pub struct InterimSelect<'a>(pub(crate) &'a Select);
+// Common table expressions, example: with t as (select a from source)
+pub struct InterimWith<'a>(pub(crate) &'a With);
+// Expression, subqueries inside are collected to the list
+pub struct InterimExpr<'a, 'b>(pub(crate) &'a SqlExpr, pub(crate) &'b mut Vec<Subquery<'a>>);
+pub struct InterimSubquery<'a, 'b>(
+    pub(crate) &'a Query,
+    pub(crate) SubqueryKind,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
 pub struct InterimOperator(pub(crate) SqlBinaryOperator);
-// Selection item, example: age > 10
-pub struct InterimSelectItem<'a>(pub(crate) &'a SelectItem);
-// Aggregation condition
-pub struct InterimFuncExprItem<'a>(pub(crate) &'a Function);
-pub struct InterimFuncArgsExprItem<'a>(pub(crate) &'a FunctionArguments);
+// Selection item, example: price * qty as total, subqueries inside are collected to the list
+pub struct InterimSelectItem<'a, 'b>(
+    pub(crate) &'a SelectItem,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
+// Function call, an aggregation or a window function
+pub struct InterimFuncExprItem<'a, 'b>(
+    pub(crate) &'a Function,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
+// Scalar function, example: coalesce(a, 0)
+pub struct InterimScalarFunc<'a, 'b>(
+    pub(crate) &'a Function,
+    pub(crate) ScalarFunc,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
+// Aggregation condition, example: sum(price * qty)
+pub struct InterimAggregation<'a, 'b>(
+    pub(crate) &'a Function,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
+// Window function, example: rank() over (partition by a order by b)
+pub struct InterimWindow<'a, 'b>(
+    pub(crate) &'a Function,
+    pub(crate) &'a WindowSpec,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
+pub struct InterimWindowFrame<'a>(pub(crate) &'a WindowSpec);
+pub struct InterimFuncArgsExprItem<'a, 'b>(
+    pub(crate) &'a FunctionArguments,
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
 // Source table
 pub struct InterimSource<'a>(pub(crate) &'a [TableWithJoins]);
-// Order & group formula, example: order by member_id
-pub struct InterimOrderBy<'a>(pub(crate) &'a OrderBy);
-pub struct InterimGroupBy<'a>(pub(crate) &'a GroupByExpr);
+pub struct InterimTable<'a>(pub(crate) &'a TableFactor);
+// Table function, example: read_csv('http://abc.xyz/a.csv', delimiter => ';')
+pub struct InterimTableFunction<'a>(pub(crate) &'a ObjectName, pub(crate) &'a TableFunctionArgs);
+pub struct InterimJoin<'a>(pub(crate) &'a SqlJoin);
+// Order & group formula, example: order by member_id, the keys may refer the projection
+pub struct InterimOrderBy<'a, 'b>(pub(crate) &'a OrderBy, pub(crate) &'b [Expr]);
+pub struct InterimGroupBy<'a, 'b>(
+    pub(crate) &'a GroupByExpr,
+    pub(crate) &'b [Expr],
+    pub(crate) &'b mut Vec<Subquery<'a>>,
+);
 // Sql limit or offset
 pub struct InterimOffset<'a>(pub(crate) &'a SqlOffset);
 pub struct InterimLimit<'a>(pub(crate) &'a SqlExpr);
 pub struct InterimValue(pub(crate) SqlValue);
+pub struct InterimTypedValue<'a>(pub(crate) &'a SqlDataType, pub(crate) &'a SqlValue);
+pub struct InterimInterval<'a>(pub(crate) &'a Interval);
+pub struct InterimDataType<'a>(pub(crate) &'a SqlDataType);
+
+/// Sql type names and their polars data types, the first name of a data type is the one
+/// reported for columns of that type
+const SQL_TYPES: [(&str, DataType); 35] = [
+    ("BOOLEAN", DataType::Boolean),
+    ("BOOL", DataType::Boolean),
+    ("TINYINT", DataType::Int8),
+    ("SMALLINT", DataType::Int16),
+    ("INT2", DataType::Int16),
+    ("INTEGER", DataType::Int32),
+    ("INT", DataType::Int32),
+    ("INT4", DataType::Int32),
+    ("BIGINT", DataType::Int64),
+    ("INT8", DataType::Int64),
+    ("INT64", DataType::Int64),
+    ("UTINYINT", DataType::UInt8),
+    ("USMALLINT", DataType::UInt16),
+    ("UINTEGER", DataType::UInt32),
+    ("UBIGINT", DataType::UInt64),
+    ("REAL", DataType::Float32),
+    ("FLOAT4", DataType::Float32),
+    ("DOUBLE", DataType::Float64),
+    ("DOUBLE PRECISION", DataType::Float64),
+    ("FLOAT", DataType::Float64),
+    ("FLOAT8", DataType::Float64),
+    ("FLOAT64", DataType::Float64),
+    ("DECIMAL", DataType::Float64),
+    ("NUMERIC", DataType::Float64),
+    ("VARCHAR", DataType::String),
+    ("TEXT", DataType::String),
+    ("STRING", DataType::String),
+    ("CHAR", DataType::String),
+    ("DATE", DataType::Date),
+    (
+        "TIMESTAMP",
+        DataType::Datetime(TimeUnit::Microseconds, None),
+    ),
+    ("DATETIME", DataType::Datetime(TimeUnit::Microseconds, None)),
+    ("INTERVAL", DataType::Duration(TimeUnit::Microseconds)),
+    ("BLOB", DataType::Binary),
+    ("BYTEA", DataType::Binary),
+    ("VARBINARY", DataType::Binary),
+];
+
+/// Find the sql type name of polars data type, the time unit of timestamp is ignored
+pub fn sql_type_name(data_type: &DataType) -> Option<&'static str> {
+    SQL_TYPES.iter().find_map(|(name, dtype)| {
+        (std::mem::discriminant(dtype) == std::mem::discriminant(data_type)).then_some(*name)
+    })
+}
 
 /// Convert sqlparser statement to Custom Sql struct
 impl<'a> TryFrom<&'a Statement> for Sql<'a> {
@@ -76,460 +525,2394 @@ impl<'a> TryFrom<&'a Statement> for Sql<'a> {
 
     fn try_from(sql: &'a Statement) -> Result<Self, Self::Error> {
         match sql {
-            Statement::Query(q) => {
-                // limit and offset
-                let (limit, offset) = match &q.limit_clause {
-                    Some(LimitClause::LimitOffset { limit, offset, .. }) => {
-                        (limit.as_ref(), offset.as_ref())
-                    }
-                    _ => (None, None),
-                };
-                let limit = limit.map(|v| InterimLimit(v).into());
-                let offset = offset.map(|v| InterimOffset(v).into());
-
-                // order by
-                let mut order_by = Vec::new();
-                let orders = q.order_by.as_ref();
-                if let Some(expr) = orders {
-                    order_by = InterimOrderBy(expr).try_into()?;
-                }
-
-                // Select, including table, selection, projection
-                let Select {
-                    from: table_with_joins,
-                    selection: where_clause,
-                    projection,
-
-                    group_by: inner_group_by,
-                    ..
-                } = match q.body.as_ref() {
-                    SetExpr::Select(statement) => statement.as_ref(),
-                    v => return Err(CustomError::SqlExpressionError(v.to_string())),
-                };
-                let source = InterimSource(table_with_joins).try_into()?;
-
-                let condition = match where_clause {
-                    Some(expr) => Some(InterimExpr(Box::new(expr.to_owned())).try_into()?),
-                    None => None,
-                };
-
-                // group by
-                let group_by: Vec<&'a str> = InterimGroupBy(inner_group_by).try_into()?;
-
-                let mut selection = Vec::new();
-                let mut aggregation = Vec::new();
-                if group_by.len() > 0 {
-                    for p in projection {
-                        let expr: Expr = InterimSelectItem(p).try_into()?;
-                        match &expr {
-                            Expr::Alias(inner_expr, column_name) => {
-                                selection.push(col(column_name.as_str()));
-                                if matches!(inner_expr.as_ref(), Expr::Agg(_))
-                                    || matches!(inner_expr.as_ref(), Expr::Len)
-                                {
-                                    aggregation.push(expr);
-                                }
-                            }
-                            _ => selection.push(expr),
-                        }
-                    }
-                } else {
-                    for p in projection {
-                        let expr = InterimSelectItem(p).try_into()?;
-                        selection.push(expr);
-                    }
-                }
-
-                Ok(Sql {
-                    selection,
-                    aggregation,
-                    source,
-                    limit,
-                    offset,
-                    condition,
-                    order_by,
-                    group_by,
-                })
-            }
-            Statement::ShowColumns {
-                extended,
-                full,
-                show_options,
-            } => {
+            Statement::Query(q) => InterimQuery(q).try_into(),
+            Statement::ShowColumns { show_options, .. } => {
                 let source = if let Some(inner) = &show_options.show_in
                     && let Some(object) = &inner.parent_name
                     && object.0.len() == 1
                     && let ObjectNamePart::Identifier(ident) = &object.0[0]
                 {
-                    ident.value.as_str()
+                    Source::Table(ident.value.as_str())
                 } else {
                     return Err(CustomError::SqlStatementError(show_options.to_string()));
                 };
 
-                Ok(Sql {
-                    selection: vec![],
-                    aggregation: vec![],
-                    source,
-                    limit: None,
-                    offset: None,
-                    condition: None,
-                    order_by: vec![],
-                    group_by: vec![],
-                })
+                Ok(source.into())
             }
             v => Err(CustomError::SqlStatementError(format!("{:?}", v))),
         }
     }
 }
 
-/// Convert SqlParser Expr To DataFrame Expr
-impl TryFrom<InterimExpr> for Expr {
+/// Convert SqlParser query to Custom Sql struct
+impl<'a> TryFrom<InterimQuery<'a>> for Sql<'a> {
     type Error = CustomError;
 
-    fn try_from(expr: InterimExpr) -> std::result::Result<Self, Self::Error> {
-        match *expr.0 {
-            SqlExpr::BinaryOp { left, op, right } => Ok(Expr::BinaryExpr {
-                left: Arc::new(InterimExpr(left).try_into()?),
-                op: InterimOperator(op).try_into()?,
-                right: Arc::new(InterimExpr(right).try_into()?),
-            }),
-            SqlExpr::Wildcard(_num) => Ok(Self::Wildcard),
-            SqlExpr::Identifier(ident) => {
-                for op in ["=", ">", ">=", "<", "<="].into_iter() {
-                    if let Some((left, right)) = ident.value.split_once(op) {
-                        let temp_op: InterimOperator = op.try_into()?;
-                        return Ok(Expr::BinaryExpr {
-                            left: Arc::new(
-                                InterimExpr(Box::new(SqlExpr::Identifier(Ident::new(left))))
-                                    .try_into()?,
-                            ),
-                            op: temp_op.try_into()?,
-                            // ToDo
-                            right: Arc::new(
-                                InterimExpr(Box::new(SqlExpr::Value(
-                                    SqlValue::Number(right.to_owned(), true).into(),
-                                )))
-                                .try_into()?,
-                            ),
-                        });
-                    }
-                }
-                Ok(Self::Column(ident.value.into()))
+    fn try_from(query: InterimQuery<'a>) -> Result<Self, Self::Error> {
+        let q = query.0;
+        // common table expressions
+        let ctes = match &q.with {
+            Some(with) => InterimWith(with).try_into()?,
+            None => vec![],
+        };
+
+        // limit and offset
+        let (limit, offset) = match &q.limit_clause {
+            Some(LimitClause::LimitOffset { limit, offset, .. }) => {
+                (limit.as_ref(), offset.as_ref())
             }
-            SqlExpr::Value(v) => Ok(Self::Literal(InterimValue(v.value).try_into()?)),
-            v => Err(CustomError::SqlExpressionError(format!("{}", v))),
-        }
-    }
-}
+            _ => (None, None),
+        };
+        let limit = limit.map(|v| InterimLimit(v).into());
+        let offset = offset.map(|v| InterimOffset(v).into());
 
-/// Convert SqlParser BinaryOperator To DataFrame Operator
-impl TryFrom<InterimOperator> for Operator {
-    type Error = CustomError;
+        // Select or set operation, order by and limit apply to the whole body
+        let mut sql: Sql<'a> = InterimSetExpr(&q.body).try_into()?;
 
-    fn try_from(op: InterimOperator) -> std::result::Result<Self, Self::Error> {
-        match op.0 {
-            SqlBinaryOperator::Plus => Ok(Self::Plus),
-            SqlBinaryOperator::Minus => Ok(Self::Minus),
-            SqlBinaryOperator::Multiply => Ok(Self::Multiply),
-            SqlBinaryOperator::Divide => Ok(Self::Divide),
-            SqlBinaryOperator::Modulo => Ok(Self::Modulus),
-            SqlBinaryOperator::Gt => Ok(Self::Gt),
-            SqlBinaryOperator::Lt => Ok(Self::Lt),
-            SqlBinaryOperator::GtEq => Ok(Self::GtEq),
-            SqlBinaryOperator::LtEq => Ok(Self::LtEq),
-            SqlBinaryOperator::Eq => Ok(Self::Eq),
-            SqlBinaryOperator::NotEq => Ok(Self::NotEq),
-            SqlBinaryOperator::And => Ok(Self::And),
-            SqlBinaryOperator::Or => Ok(Self::Or),
-            v => Err(CustomError::SqlOperatorError(v.to_string())),
+        // order by, the keys can be ordinals or aliases of the projection
+        let mut order_by = match q.order_by.as_ref() {
+            Some(expr) => InterimOrderBy(expr, &sql.selection).try_into()?,
+            None => Vec::new(),
+        };
+        if !sql.group_by.is_empty() || !sql.aggregation.is_empty() {
+            order_by = order_by
+                .into_iter()
+                .map(|(expr, order_type, nulls_order)| {
+                    let expr = replace_group_key(expr, &sql.group_by);
+                    let expr = hide_aggregation(expr, &mut sql.aggregation);
+                    (expr, order_type, nulls_order)
+                })
+                .collect();
         }
-    }
-}
-
-/// Convert &str(>,>=,<,<=,=) to Interim Operation
-impl TryFrom<&str> for InterimOperator {
-    type Error = CustomError;
 
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        match value {
-            ">" => Ok(InterimOperator(SqlBinaryOperator::Gt)),
-            ">=" => Ok(InterimOperator(SqlBinaryOperator::GtEq)),
-            "=" => Ok(InterimOperator(SqlBinaryOperator::Eq)),
-            "<" => Ok(InterimOperator(SqlBinaryOperator::Lt)),
-            "<=" => Ok(InterimOperator(SqlBinaryOperator::LtEq)),
-            "!=" => Ok(InterimOperator(SqlBinaryOperator::NotEq)),
-            _ => Err(CustomError::SqlOperatorError(value.to_owned())),
-        }
+        Ok(Sql {
+            limit,
+            offset,
+            order_by,
+            ctes,
+            ..sql
+        })
     }
 }
 
-/// Convert SqlParser SelectItem to Expr of polars
-impl<'a> TryFrom<InterimSelectItem<'a>> for Expr {
+/// Convert SqlParser query body to Custom Sql struct, set operation is wrapped as
+/// `select * from (left union right)`
+impl<'a> TryFrom<InterimSetExpr<'a>> for Sql<'a> {
     type Error = CustomError;
 
-    fn try_from(p: InterimSelectItem<'a>) -> std::result::Result<Self, Self::Error> {
-        match p.0 {
-            SelectItem::UnnamedExpr(SqlExpr::Identifier(id)) => Ok(col(&id.to_string())),
-            SelectItem::UnnamedExpr(SqlExpr::Function(inner_func)) => {
-                let expr = InterimFuncExprItem(inner_func).try_into()?;
-                let column_name: String = InterimFuncArgsExprItem(&inner_func.args).try_into()?;
-                // Aggregation columns require alias name
-                let target_column_name = format!("{}_agg", column_name.as_str().to_string());
-                Ok(Expr::Alias(Arc::new(expr), target_column_name.into()))
-            }
-            SelectItem::ExprWithAlias {
-                expr: SqlExpr::Identifier(id),
-                alias,
-            } => Ok(Expr::Alias(
-                Arc::new(Expr::Column((&id.value).into())),
-                alias.value.to_owned().into(),
-            )),
-            SelectItem::ExprWithAlias {
-                expr: SqlExpr::Function(inner_func),
-                alias,
+    fn try_from(body: InterimSetExpr<'a>) -> Result<Self, Self::Error> {
+        match body.0 {
+            SetExpr::Select(select) => InterimSelect(select).try_into(),
+            SetExpr::Query(q) => Ok(Source::Query(Box::new(InterimQuery(q).try_into()?)).into()),
+            SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
             } => {
-                let temp_expr: Expr = InterimFuncExprItem(inner_func).try_into()?;
-                Ok(Expr::Alias(
-                    Arc::new(temp_expr),
-                    alias.value.to_owned().into(),
-                ))
-            }
-            SelectItem::Wildcard(wildcard_options) => {
-                let token_with_span = wildcard_options.wildcard_token.0.clone();
-                let token = token_with_span.token;
-                match token {
-                    Token::Mul => Ok(Expr::Wildcard),
-                    _ => return Err(CustomError::SqlSelectItemError(token.to_string())),
+                let op = match op {
+                    SetOperator::Union => SetOperation::Union,
+                    SetOperator::Intersect => SetOperation::Intersect,
+                    SetOperator::Except | SetOperator::Minus => SetOperation::Except,
+                };
+                let all = match set_quantifier {
+                    SetQuantifier::All => true,
+                    SetQuantifier::Distinct | SetQuantifier::None => false,
+                    v => {
+                        return Err(CustomError::SqlSetOperationError(format!("{} {}", op, v)));
+                    }
+                };
+                if all && op != SetOperation::Union {
+                    return Err(CustomError::SqlSetOperationError(format!("{} ALL", op)));
+                }
+
+                Ok(Source::Set {
+                    left: Box::new(InterimSetExpr(left).try_into()?),
+                    right: Box::new(InterimSetExpr(right).try_into()?),
+                    op,
+                    all,
                 }
+                .into())
             }
-            item => Err(CustomError::SqlSelectItemError(item.to_string())),
+            v => Err(CustomError::SqlExpressionError(v.to_string())),
         }
     }
 }
 
-impl<'a> TryFrom<InterimFuncExprItem<'a>> for Expr {
+/// Convert SqlParser select to Custom Sql struct, including table, selection, projection
+impl<'a> TryFrom<InterimSelect<'a>> for Sql<'a> {
     type Error = CustomError;
 
-    fn try_from(v: InterimFuncExprItem<'a>) -> std::result::Result<Self, Self::Error> {
-        let Function { name, args, .. } = v.0;
+    fn try_from(select: InterimSelect<'a>) -> Result<Self, Self::Error> {
+        let Select {
+            distinct,
+            from: table_with_joins,
+            selection: where_clause,
+            projection,
 
-        // get aggregation func name
-        let agg_func = if let ObjectNamePart::Identifier(ident) = &name.0[0] {
-            AggFunc::from_str(&ident.value).ok_or_else(|| {
-                CustomError::SqlExprFuncItem(format!(
-                    "Unsupported aggregation function: {}",
-                    ident.value
-                ))
-            })?
-        } else {
-            return Err(CustomError::SqlExprFuncItem(format!(
-                "Invalid aggregation function name ({:?})",
-                &name.0
-            )));
+            group_by: inner_group_by,
+            having,
+            ..
+        } = select.0;
+        let source: Source<'a> = InterimSource(table_with_joins).try_into()?;
+
+        let mut subqueries = Vec::new();
+        let condition = match where_clause {
+            Some(expr) => Some(InterimExpr(expr, &mut subqueries).try_into()?),
+            None => None,
         };
 
-        // get column name
-        let column_name: String = InterimFuncArgsExprItem(args).try_into()?;
+        let projection = projection
+            .iter()
+            .map(|p| InterimSelectItem(p, &mut subqueries).try_into())
+            .collect::<Result<Vec<Expr>, CustomError>>()?;
 
-        // generate aggregation
-        match agg_func {
-            AggFunc::Max => Ok(col(&column_name).max()),
-            AggFunc::Min => Ok(col(&column_name).min()),
-            AggFunc::Sum => Ok(col(&column_name).sum()),
-            AggFunc::Avg => Ok(col(&column_name).mean()),
-            AggFunc::Count => {
-                if column_name == "*" {
-                    Ok(len())
-                } else {
-                    Ok(col(&column_name).count())
+        // group by, the keys may refer to the projection by ordinal or alias
+        let group_by: Vec<Expr> =
+            InterimGroupBy(inner_group_by, &projection, &mut subqueries).try_into()?;
+
+        // aggregated query, the projection is computed from the groups, or from the whole
+        // table as a single row when there is no group by
+        let mut selection = Vec::new();
+        let mut aggregation = Vec::new();
+        if !group_by.is_empty() || projection.iter().any(has_aggregation) {
+            for expr in projection {
+                match &expr {
+                    Expr::Alias(inner_expr, column_name) if is_aggregation(inner_expr) => {
+                        selection.push(col(column_name.as_str()));
+                        aggregation.push(expr);
+                    }
+                    // aggregations nested in expressions are computed as hidden columns
+                    _ => {
+                        let expr = replace_group_key(expr, &group_by);
+                        selection.push(hide_aggregation(expr, &mut aggregation))
+                    }
                 }
             }
+        } else {
+            selection = projection;
         }
-    }
-}
-
-impl<'a> TryFrom<InterimFuncArgsExprItem<'a>> for String {
-    type Error = CustomError;
 
-    fn try_from(args: InterimFuncArgsExprItem) -> std::result::Result<Self, Self::Error> {
-        let column_name = match args.0 {
-            FunctionArguments::List(FunctionArgumentList {
-                args: inner_args, ..
-            }) if !inner_args.is_empty() => match &inner_args[0] {
-                FunctionArg::Unnamed(FunctionArgExpr::Expr(SqlExpr::Identifier(ident))) => {
-                    ident.value.clone()
+        // having, aggregations are computed with the groups and referenced by hidden columns
+        let having = match having {
+            Some(expr) => {
+                let temp_expr = InterimExpr(expr, &mut subqueries).try_into()?;
+                let temp_expr = replace_group_key(temp_expr, &group_by);
+                let temp_expr = hide_aggregation(temp_expr, &mut aggregation);
+                if group_by.is_empty() && aggregation.is_empty() {
+                    return Err(CustomError::SqlExpressionError(format!(
+                        "HAVING {} without GROUP BY",
+                        expr
+                    )));
                 }
-                FunctionArg::Unnamed(FunctionArgExpr::Wildcard) => "*".to_string(),
-                _ => {
-                    return Err(CustomError::SqlExprFuncArgsItem(format!(
-                        "Aggregation function requires argument ({:?})",
-                        &inner_args[0]
+                Some(temp_expr)
+            }
+            None => None,
+        };
+
+        // window functions are computed on the rows of source or groups
+        let mut window = Vec::new();
+        let selection: Vec<Expr> = selection
+            .into_iter()
+            .map(|expr| hide_window(expr, &mut window))
+            .collect();
+
+        // global aggregation returns a single row, bare columns have no value to take
+        if group_by.is_empty() && !aggregation.is_empty() {
+            let outputs: Vec<PlSmallStr> = aggregation
+                .iter()
+                .filter_map(|expr| match expr {
+                    Expr::Alias(_, name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            for expr in &selection {
+                if let Some(name) = bare_column(expr, &outputs) {
+                    return Err(CustomError::SqlAggregationError(format!(
+                        "bare column {} without GROUP BY",
+                        name
                     )));
                 }
-            },
-            _ => {
-                return Err(CustomError::SqlExprFuncArgsItem(format!(
-                    "Aggregation function requires arguments ({:?})",
-                    &args.0
-                )));
             }
+        }
+
+        // distinct, the key of distinct on must be a column
+        let distinct = match distinct {
+            Some(SqlDistinct::Distinct) => Some(Distinct::All),
+            Some(SqlDistinct::On(exprs)) => Some(Distinct::On(
+                exprs
+                    .iter()
+                    .map(|expr| match expr {
+                        SqlExpr::Identifier(id) => Ok(id.value.as_str()),
+                        v => Err(CustomError::SqlExpressionError(format!(
+                            "DISTINCT ON {}",
+                            v
+                        ))),
+                    })
+                    .collect::<Result<Vec<&'a str>, CustomError>>()?,
+            )),
+            None => None,
         };
 
-        Ok(column_name)
+        Ok(Sql {
+            selection,
+            aggregation,
+            window,
+            distinct,
+            condition,
+            having,
+            group_by,
+            subqueries,
+            ..source.into()
+        })
     }
 }
 
-impl<'a> TryFrom<InterimSource<'a>> for &'a str {
-    type Error = CustomError;
+/// Whether the expression is an aggregation, some aggregations are functions of polars
+fn is_aggregation(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Agg(_)
+            | Expr::Len
+            | Expr::Function {
+                function: FunctionExpr::ApproxNUnique
+                    | FunctionExpr::StringExpr(StringFunction::ConcatVertical { .. }),
+                ..
+            }
+    )
+}
 
-    fn try_from(source: InterimSource<'a>) -> Result<Self, Self::Error> {
-        // ToDo
-        if source.0.len() != 1 {
-            return Err(CustomError::SqlTableError("empty".to_string()));
-        }
+/// Name of qualified column, example: a.b
+fn qualified_name(idents: &[Ident]) -> String {
+    idents
+        .iter()
+        .map(|ident| ident.value.as_str())
+        .collect::<Vec<&str>>()
+        .join(".")
+}
 
-        let table = &source.0[0];
-        if !table.joins.is_empty() {
-            return Err(CustomError::SqlTableError(format!(
-                "joint table {:?}",
-                table.joins
-            )));
-        }
+/// Match name to the available column names ignoring case when there is no exact match,
+/// a name matching several columns is ambiguous and an unknown name is kept
+pub(crate) fn resolve_name_case(
+    name: &str,
+    names: &[PlSmallStr],
+) -> Result<PlSmallStr, CustomError> {
+    if names.iter().any(|n| n == name) {
+        return Ok(name.into());
+    }
+    let mut matched = names
+        .iter()
+        .filter(|n| n.eq_ignore_ascii_case(name))
+        .collect::<Vec<&PlSmallStr>>();
+    matched.sort();
+    matched.dedup();
+    match matched.as_slice() {
+        [] => Ok(name.into()),
+        [n] => Ok((*n).clone()),
+        matched => Err(CustomError::SqlAmbiguousColumnError(format!(
+            "{} matches {}",
+            name,
+            matched
+                .iter()
+                .map(|n| n.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ))),
+    }
+}
 
-        match &table.relation {
-            TableFactor::Table { name, .. } => {
-                let Some(ObjectNamePart::Identifier(ident)) = &name.0.first() else {
-                    return Err(CustomError::SqlTableError(format!("{:?}", &name.0)));
-                };
-                Ok(&ident.value)
+/// Match the columns of expression to the available column names ignoring case
+pub(crate) fn resolve_case(expr: Expr, names: &[PlSmallStr]) -> Result<Expr, CustomError> {
+    let mut error = None;
+    let expr = expr.map_expr(|e| match e {
+        Expr::Column(name) => match resolve_name_case(&name, names) {
+            Ok(name) => Expr::Column(name),
+            Err(e) => {
+                error.get_or_insert(e);
+                Expr::Column(name)
             }
-            v => Err(CustomError::SqlTableError(format!("{:?}", v))),
+        },
+        e => e,
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(expr),
+    }
+}
+
+impl Sql<'_> {
+    /// Match the columns of the query to the column names of its source ignoring case,
+    /// the names of distinct on are matched by the planner
+    pub(crate) fn resolve_case(&mut self, names: &[PlSmallStr]) -> Result<(), CustomError> {
+        let exprs = self
+            .condition
+            .iter_mut()
+            .chain(self.having.iter_mut())
+            .chain(self.selection.iter_mut())
+            .chain(self.aggregation.iter_mut())
+            .chain(self.window.iter_mut())
+            .chain(self.group_by.iter_mut())
+            .chain(self.order_by.iter_mut().map(|(expr, ..)| expr));
+        for expr in exprs {
+            *expr = resolve_case(expr.clone(), names)?;
         }
+
+        Ok(())
     }
 }
 
-/// Convert SqlParser order by expr to Vec<(String, OrderType)>
-impl<'a> TryFrom<InterimOrderBy<'a>> for Vec<(&'a str, OrderType)> {
-    type Error = CustomError;
+/// Whether the expression contains an aggregation out of window functions
+fn has_aggregation(expr: &Expr) -> bool {
+    let mut aggregation = Vec::new();
+    let _ = hide_aggregation(expr.clone(), &mut aggregation);
+    !aggregation.is_empty()
+}
 
-    fn try_from(o: InterimOrderBy<'a>) -> Result<Self, Self::Error> {
-        let order_list = match &o.0.kind {
-            OrderByKind::Expressions(order_by_list) => {
-                let order_list = order_by_list.iter().rfold(
-                    Vec::new(),
-                    |mut acc: Vec<(&'a str, OrderType)>, order_by| {
-                        if let SqlExpr::Identifier(id) = &order_by.expr {
-                            let order_type = if let Some(is_asc) = order_by.options.asc {
-                                if is_asc == true {
-                                    OrderType::Asc
-                                } else {
-                                    OrderType::Desc
-                                }
-                            } else if let Some((_, order_type)) = acc.last() {
-                                order_type.to_owned()
-                            } else {
-                                OrderType::Desc
-                            };
-                            acc.push((id.value.as_str(), order_type));
-                        } else {
-                            // return Err(CustomError::SqlOrderError(order_by.expr);
-                            println!(
-                                "We only support identifier for order by, get {}",
-                                &order_by.expr
-                            );
-                        }
-                        acc
-                    },
-                );
+/// Find the column of aggregated expression which is neither an aggregation output
+/// nor a subquery placeholder
+fn bare_column(expr: &Expr, outputs: &[PlSmallStr]) -> Option<String> {
+    expr.into_iter().find_map(|e| match e {
+        Expr::Column(name) if !outputs.contains(name) && !name.starts_with("__subquery_") => {
+            Some(name.to_string())
+        }
+        Expr::Wildcard => Some("*".to_string()),
+        _ => None,
+    })
+}
 
-                order_list.iter().rev().cloned().collect()
-            }
-            _ => vec![],
-        };
+/// Replace aggregations of grouped query expression with hidden columns, the aggregations
+/// are appended to the list so that they are computed with the groups
+fn hide_aggregation(expr: Expr, aggregation: &mut Vec<Expr>) -> Expr {
+    let mut hider = AggregationHider(aggregation);
+    // hiding never fails
+    expr.rewrite(&mut hider, &mut ()).unwrap()
+}
+
+/// Rewriter of hide_aggregation, aggregations inside window functions are computed
+/// with the partitions and are kept
+struct AggregationHider<'a>(&'a mut Vec<Expr>);
+
+impl RewritingVisitor for AggregationHider<'_> {
+    type Node = Expr;
+    type Arena = ();
+
+    fn pre_visit(&mut self, node: &Expr, _arena: &mut ()) -> PolarsResult<RewriteRecursion> {
+        Ok(match node {
+            Expr::Window { .. } => RewriteRecursion::Stop,
+            e if is_aggregation(e) => RewriteRecursion::MutateAndStop,
+            _ => RewriteRecursion::NoMutateAndContinue,
+        })
+    }
 
-        Ok(order_list)
+    fn mutate(&mut self, node: Expr, _arena: &mut ()) -> PolarsResult<Expr> {
+        let name = format!("__agg_{}", self.0.len());
+        self.0.push(node.alias(name.as_str()));
+        Ok(col(name))
     }
 }
 
-/// Convert SqlParser offset expr to i64
-impl<'a> From<InterimOffset<'a>> for i64 {
-    fn from(offset: InterimOffset<'a>) -> Self {
-        match offset.0 {
-            SqlOffset {
-                value:
-                    SqlExpr::Value(ValueWithSpan {
-                        value: SqlValue::Number(v, _b),
-                        ..
-                    }),
-                ..
-            } => v.parse().unwrap_or(0),
-            _ => 0,
-        }
+/// Replace the expressions of group keys with the key columns, aggregations are computed
+/// from the rows of groups and are kept
+fn replace_group_key(expr: Expr, group_by: &[Expr]) -> Expr {
+    let mut replacer = GroupKeyReplacer(group_by);
+    // replacing never fails
+    expr.rewrite(&mut replacer, &mut ()).unwrap()
+}
+
+/// Rewriter of replace_group_key
+struct GroupKeyReplacer<'a>(&'a [Expr]);
+
+impl GroupKeyReplacer<'_> {
+    fn key(&self, node: &Expr) -> Option<PlSmallStr> {
+        self.0.iter().find_map(|key| match key {
+            Expr::Alias(inner_expr, name) if inner_expr.as_ref() == node => Some(name.clone()),
+            _ => None,
+        })
     }
 }
 
-/// Convert to SqlParser group by to string vec
-impl<'a> TryFrom<InterimGroupBy<'a>> for Vec<&'a str> {
-    type Error = CustomError;
+impl RewritingVisitor for GroupKeyReplacer<'_> {
+    type Node = Expr;
+    type Arena = ();
 
-    fn try_from(g: InterimGroupBy<'a>) -> std::result::Result<Self, Self::Error> {
-        let list = match &g.0 {
-            GroupByExpr::Expressions(expr_list, _) => {
-                expr_list
-                    .iter()
-                    .fold(Vec::new(), |mut acc: Vec<&'a str>, group_by| {
-                        if let SqlExpr::Identifier(id) = group_by {
-                            acc.push(id.value.as_str());
-                        }
-                        acc
-                    })
-            }
-            _ => vec![],
-        };
+    fn pre_visit(&mut self, node: &Expr, _arena: &mut ()) -> PolarsResult<RewriteRecursion> {
+        Ok(match node {
+            e if is_aggregation(e) => RewriteRecursion::Stop,
+            e if self.key(e).is_some() => RewriteRecursion::MutateAndStop,
+            _ => RewriteRecursion::NoMutateAndContinue,
+        })
+    }
 
-        Ok(list)
+    fn mutate(&mut self, node: Expr, _arena: &mut ()) -> PolarsResult<Expr> {
+        Ok(self.key(&node).map(col).unwrap_or(node))
     }
 }
 
-/// Convert SqlParser limit expr to usize
-impl<'a> From<InterimLimit<'a>> for usize {
-    fn from(l: InterimLimit<'a>) -> Self {
-        match l.0 {
-            SqlExpr::Value(ValueWithSpan { value, .. }) => {
-                if let SqlValue::Number(v, _b) = value {
-                    v.parse().unwrap_or(usize::MAX)
-                } else {
-                    100
-                }
-            }
-            _ => usize::MAX,
+/// Replace window functions with hidden columns, the windows are appended to the list so
+/// that they are computed before the order and limit of query
+fn hide_window(expr: Expr, window: &mut Vec<Expr>) -> Expr {
+    expr.map_expr(|e| match e {
+        Expr::Window { .. } => {
+            let name = format!("__window_{}", window.len());
+            window.push(e.alias(name.as_str()));
+            col(name)
+        }
+        e => e,
+    })
+}
+
+/// Select all columns of source, example: select * from source
+impl<'a> From<Source<'a>> for Sql<'a> {
+    fn from(source: Source<'a>) -> Self {
+        Sql {
+            selection: vec![Expr::Wildcard],
+            aggregation: vec![],
+            window: vec![],
+            source,
+            limit: None,
+            offset: None,
+            distinct: None,
+            condition: None,
+            having: None,
+            order_by: vec![],
+            group_by: vec![],
+            subqueries: vec![],
+            ctes: vec![],
         }
     }
 }
 
-/// Convert SqlParser Value to LiteralValue of polars
-impl TryFrom<InterimValue> for LiteralValue {
+/// Convert SqlParser with clause to common table expressions, the order is kept
+/// so that every expression can refer to the previous ones
+impl<'a> TryFrom<InterimWith<'a>> for Vec<Cte<'a>> {
     type Error = CustomError;
 
-    fn try_from(value: InterimValue) -> Result<Self, Self::Error> {
-        match value.0 {
-            SqlValue::Number(v, _) => Ok(LiteralValue::Dyn(DynLiteralValue::Float(
-                v.parse().unwrap_or_default(),
-            ))),
-            SqlValue::SingleQuotedString(v) => {
-                Ok(LiteralValue::Dyn(DynLiteralValue::Str(v.into())))
+    fn try_from(with: InterimWith<'a>) -> Result<Self, Self::Error> {
+        if with.0.recursive {
+            return Err(CustomError::SqlCteError(with.0.to_string()));
+        }
+
+        let mut ctes: Vec<Cte<'a>> = Vec::new();
+        for cte in &with.0.cte_tables {
+            let name = cte.alias.name.value.as_str();
+            if ctes.iter().any(|item| item.name == name) {
+                return Err(CustomError::SqlCteError(format!("duplicate name {}", name)));
             }
-            v => Err(CustomError::SqlValueError(format!("{}", v))),
+            ctes.push(Cte {
+                name,
+                columns: cte
+                    .alias
+                    .columns
+                    .iter()
+                    .map(|column| column.name.value.as_str())
+                    .collect(),
+                sql: InterimQuery(&cte.query).try_into()?,
+            });
         }
+
+        Ok(ctes)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::TyrDialect;
-    use sqlparser::parser::Parser;
-
+/// Convert SqlParser Expr To DataFrame Expr
+impl<'a> TryFrom<InterimExpr<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(expr: InterimExpr<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimExpr(sql_expr, subqueries) = expr;
+        match sql_expr {
+            // regular expression match of postgres, example: name ~* '^a'
+            SqlExpr::BinaryOp {
+                left,
+                op:
+                    op @ (SqlBinaryOperator::PGRegexMatch
+                    | SqlBinaryOperator::PGRegexIMatch
+                    | SqlBinaryOperator::PGRegexNotMatch
+                    | SqlBinaryOperator::PGRegexNotIMatch),
+                right,
+            } => {
+                let inner_expr: Expr = InterimExpr(left, subqueries).try_into()?;
+                let pattern: Expr = InterimExpr(right, subqueries).try_into()?;
+                let pattern = match op {
+                    SqlBinaryOperator::PGRegexIMatch | SqlBinaryOperator::PGRegexNotIMatch => {
+                        lit("(?i)") + pattern
+                    }
+                    _ => pattern,
+                };
+                let expr = inner_expr.str().contains(pattern, true);
+                Ok(match op {
+                    SqlBinaryOperator::PGRegexNotMatch | SqlBinaryOperator::PGRegexNotIMatch => {
+                        expr.not()
+                    }
+                    _ => expr,
+                })
+            }
+            // null in any side makes the result null
+            SqlExpr::BinaryOp {
+                left,
+                op: SqlBinaryOperator::StringConcat,
+                right,
+            } => Ok(concat_str(
+                [
+                    InterimExpr(left, subqueries).try_into()?,
+                    InterimExpr(right, subqueries).try_into()?,
+                ],
+                "",
+                false,
+            )),
+            SqlExpr::BinaryOp { left, op, right } => {
+                let left: Expr = InterimExpr(left, subqueries).try_into()?;
+                let op: Operator = InterimOperator(op.to_owned()).try_into()?;
+                let right: Expr = InterimExpr(right, subqueries).try_into()?;
+                let (left, right) = if op.is_comparison() {
+                    coerce_temporal(left, right)
+                } else {
+                    (left, right)
+                };
+                Ok(Expr::BinaryExpr {
+                    left: Arc::new(left),
+                    op,
+                    right: Arc::new(right),
+                })
+            }
+            SqlExpr::Nested(inner_expr) => InterimExpr(inner_expr, subqueries).try_into(),
+            SqlExpr::UnaryOp {
+                op,
+                expr: inner_expr,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                match op {
+                    UnaryOperator::Not => Ok(inner_expr.not()),
+                    UnaryOperator::Minus => Ok(-inner_expr),
+                    UnaryOperator::Plus => Ok(inner_expr),
+                    v => Err(CustomError::SqlOperatorError(v.to_string())),
+                }
+            }
+            SqlExpr::IsNull(inner_expr) => {
+                Ok(Expr::try_from(InterimExpr(inner_expr, subqueries))?.is_null())
+            }
+            SqlExpr::IsNotNull(inner_expr) => {
+                Ok(Expr::try_from(InterimExpr(inner_expr, subqueries))?.is_not_null())
+            }
+            // unknown is neither true nor false
+            SqlExpr::IsTrue(inner_expr) | SqlExpr::IsNotTrue(inner_expr) => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let expr = inner_expr.eq(lit(true)).fill_null(lit(false));
+                Ok(if matches!(sql_expr, SqlExpr::IsTrue(_)) {
+                    expr
+                } else {
+                    expr.not()
+                })
+            }
+            SqlExpr::IsFalse(inner_expr) | SqlExpr::IsNotFalse(inner_expr) => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let expr = inner_expr.eq(lit(false)).fill_null(lit(false));
+                Ok(if matches!(sql_expr, SqlExpr::IsFalse(_)) {
+                    expr
+                } else {
+                    expr.not()
+                })
+            }
+            SqlExpr::IsDistinctFrom(left, right) => {
+                Ok(Expr::try_from(InterimExpr(left, subqueries))?
+                    .neq_missing(Expr::try_from(InterimExpr(right, subqueries))?))
+            }
+            SqlExpr::IsNotDistinctFrom(left, right) => {
+                Ok(Expr::try_from(InterimExpr(left, subqueries))?
+                    .eq_missing(Expr::try_from(InterimExpr(right, subqueries))?))
+            }
+            // in list is the disjunction of equalities, so that nulls are unknown
+            SqlExpr::InList {
+                expr: inner_expr,
+                list,
+                negated,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let mut expr: Option<Expr> = None;
+                for item in list {
+                    let equal = inner_expr
+                        .clone()
+                        .eq(Expr::try_from(InterimExpr(item, subqueries))?);
+                    expr = Some(match expr {
+                        Some(expr) => expr.or(equal),
+                        None => equal,
+                    });
+                }
+                let expr =
+                    expr.ok_or_else(|| CustomError::SqlExpressionError(format!("{}", sql_expr)))?;
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            SqlExpr::Between {
+                expr: inner_expr,
+                negated,
+                low,
+                high,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let (value, low) =
+                    coerce_temporal(inner_expr.clone(), InterimExpr(low, subqueries).try_into()?);
+                let (inner_expr, high) =
+                    coerce_temporal(inner_expr, InterimExpr(high, subqueries).try_into()?);
+                let expr = value.gt_eq(low).and(inner_expr.lt_eq(high));
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            SqlExpr::Like {
+                negated,
+                any: false,
+                expr: inner_expr,
+                pattern,
+                escape_char,
+            }
+            | SqlExpr::ILike {
+                negated,
+                any: false,
+                expr: inner_expr,
+                pattern,
+                escape_char,
+            }
+            | SqlExpr::SimilarTo {
+                negated,
+                expr: inner_expr,
+                pattern,
+                escape_char,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let pattern = pattern_regex(
+                    pattern,
+                    escape_char.as_ref(),
+                    matches!(sql_expr, SqlExpr::SimilarTo { .. }),
+                )?;
+                let pattern = if matches!(sql_expr, SqlExpr::ILike { .. }) {
+                    format!("(?i){}", pattern)
+                } else {
+                    pattern
+                };
+                let expr = inner_expr.str().contains(lit(pattern), true);
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            SqlExpr::RLike {
+                negated,
+                expr: inner_expr,
+                pattern,
+                ..
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let expr = inner_expr
+                    .str()
+                    .contains(InterimExpr(pattern, subqueries).try_into()?, true);
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            SqlExpr::Wildcard(_num) => Ok(Self::Wildcard),
+            // qualified column, example: a.b, resolved by the planner
+            SqlExpr::CompoundIdentifier(idents) => Ok(col(qualified_name(idents))),
+            SqlExpr::Identifier(ident) => Ok(Self::Column(ident.value.as_str().into())),
+            SqlExpr::Value(v) => Ok(Self::Literal(InterimValue(v.value.to_owned()).try_into()?)),
+            SqlExpr::TypedString { data_type, value } => Ok(Self::Literal(
+                InterimTypedValue(data_type, &value.value).try_into()?,
+            )),
+            SqlExpr::Interval(interval) => Ok(Self::Literal(InterimInterval(interval).try_into()?)),
+            SqlExpr::Function(func) => InterimFuncExprItem(func, subqueries).try_into(),
+            // trim whitespace or the given characters, example: trim(leading '0' from code)
+            SqlExpr::Trim {
+                expr: inner_expr,
+                trim_where,
+                trim_what,
+                trim_characters,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let matches = match (trim_what, trim_characters.as_deref()) {
+                    (Some(what), None) => InterimExpr(what, subqueries).try_into()?,
+                    (None, Some([characters])) => InterimExpr(characters, subqueries).try_into()?,
+                    (None, None) => lit(NULL),
+                    _ => return Err(CustomError::SqlExpressionError(format!("{}", sql_expr))),
+                };
+                Ok(match trim_where {
+                    Some(TrimWhereField::Leading) => inner_expr.str().strip_chars_start(matches),
+                    Some(TrimWhereField::Trailing) => inner_expr.str().strip_chars_end(matches),
+                    Some(TrimWhereField::Both) | None => inner_expr.str().strip_chars(matches),
+                })
+            }
+            // ceil and floor are keywords, example: ceil(price)
+            SqlExpr::Ceil {
+                expr: inner_expr,
+                field: CeilFloorKind::DateTimeField(DateTimeField::NoDateTime),
+            } => Ok(Expr::try_from(InterimExpr(inner_expr, subqueries))?.ceil()),
+            SqlExpr::Floor {
+                expr: inner_expr,
+                field: CeilFloorKind::DateTimeField(DateTimeField::NoDateTime),
+            } => Ok(Expr::try_from(InterimExpr(inner_expr, subqueries))?.floor()),
+            // example: extract(year from day)
+            SqlExpr::Extract {
+                field,
+                expr: inner_expr,
+                ..
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                date_part(&field.to_string(), inner_expr)
+                    .ok_or_else(|| CustomError::SqlExpressionError(format!("{}", sql_expr)))
+            }
+            // example: substring(name from 2 for 3)
+            SqlExpr::Substring {
+                expr: inner_expr,
+                substring_from,
+                substring_for,
+                ..
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let start = match substring_from {
+                    Some(start) => Some(InterimExpr(start, subqueries).try_into()?),
+                    None => None,
+                };
+                let length = match substring_for {
+                    Some(length) => Some(InterimExpr(length, subqueries).try_into()?),
+                    None => None,
+                };
+                Ok(substring(inner_expr, start, length))
+            }
+            // try cast yields null when the value can not be converted
+            SqlExpr::Cast {
+                kind,
+                expr: inner_expr,
+                data_type,
+                format: None,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let data_type: DataType = InterimDataType(data_type).try_into()?;
+                Ok(match kind {
+                    CastKind::Cast | CastKind::DoubleColon => inner_expr.strict_cast(data_type),
+                    CastKind::TryCast | CastKind::SafeCast => inner_expr.cast(data_type),
+                })
+            }
+            // simple case compares the operand with each value, the first matched result is taken
+            SqlExpr::Case {
+                operand,
+                conditions,
+                else_result,
+                ..
+            } => {
+                let operand: Option<Expr> = match operand {
+                    Some(operand) => Some(InterimExpr(operand, subqueries).try_into()?),
+                    None => None,
+                };
+                let mut case_expr = match else_result {
+                    Some(else_result) => InterimExpr(else_result, subqueries).try_into()?,
+                    None => lit(NULL),
+                };
+                for case_when in conditions.iter().rev() {
+                    let condition: Expr =
+                        InterimExpr(&case_when.condition, subqueries).try_into()?;
+                    let condition = match &operand {
+                        Some(operand) => operand.clone().eq(condition),
+                        None => condition,
+                    };
+                    let result: Expr = InterimExpr(&case_when.result, subqueries).try_into()?;
+                    case_expr = when(condition).then(result).otherwise(case_expr);
+                }
+                Ok(case_expr)
+            }
+            SqlExpr::Subquery(query) => {
+                InterimSubquery(query, SubqueryKind::Scalar, subqueries).try_into()
+            }
+            SqlExpr::InSubquery {
+                expr: inner_expr,
+                subquery,
+                negated,
+            } => {
+                let inner_expr: Expr = InterimExpr(inner_expr, subqueries).try_into()?;
+                let list: Expr =
+                    InterimSubquery(subquery, SubqueryKind::List, subqueries).try_into()?;
+                let expr = in_list(inner_expr, list);
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            SqlExpr::Exists { subquery, negated } => {
+                let expr: Expr =
+                    InterimSubquery(subquery, SubqueryKind::Exists, subqueries).try_into()?;
+                Ok(if *negated { expr.not() } else { expr })
+            }
+            v => Err(CustomError::SqlExpressionError(format!("{}", v))),
+        }
+    }
+}
+
+/// Convert pattern of like or similar to to anchored regular expression, `%` matches any
+/// characters and `_` matches a single character, similar to keeps the other regular
+/// expression operators
+fn pattern_regex(
+    pattern: &SqlExpr,
+    escape_char: Option<&SqlValue>,
+    similar: bool,
+) -> Result<String, CustomError> {
+    let SqlExpr::Value(ValueWithSpan {
+        value: SqlValue::SingleQuotedString(pattern),
+        ..
+    }) = pattern
+    else {
+        return Err(CustomError::SqlExpressionError(format!(
+            "pattern {}",
+            pattern
+        )));
+    };
+    let escape_char = match escape_char {
+        Some(SqlValue::SingleQuotedString(v)) if v.chars().count() == 1 => v.chars().next(),
+        Some(v) => return Err(CustomError::SqlExpressionError(format!("escape {}", v))),
+        None => None,
+    };
+
+    let mut regex = String::from("(?s)^");
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ch if Some(ch) == escape_char => match chars.next() {
+                Some(escaped) => push_literal(&mut regex, escaped),
+                None => {
+                    return Err(CustomError::SqlExpressionError(format!(
+                        "pattern {} ends with escape",
+                        pattern
+                    )));
+                }
+            },
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '|' | '*' | '+' | '?' | '{' | '}' | '(' | ')' | '[' | ']' if similar => regex.push(ch),
+            ch => push_literal(&mut regex, ch),
+        }
+    }
+    regex.push('$');
+    Ok(regex)
+}
+
+/// Append character to regular expression, the meta characters are escaped
+fn push_literal(regex: &mut String, ch: char) {
+    if "\\.+*?()|[]{}^$#&-~".contains(ch) {
+        regex.push('\\');
+    }
+    regex.push(ch);
+}
+
+/// Convert SqlParser subquery to placeholder column, the subquery is pushed to the list
+impl<'a> TryFrom<InterimSubquery<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(subquery: InterimSubquery<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimSubquery(query, kind, subqueries) = subquery;
+        let sql: Sql<'a> = InterimQuery(query).try_into()?;
+        let name = format!("__subquery_{}", subqueries.len());
+        let expr = col(name.as_str());
+        subqueries.push(Subquery { name, kind, sql });
+        Ok(expr)
+    }
+}
+
+/// Membership of the list collected by subquery, a value out of the list is unknown when
+/// the value or an item of the list is null
+fn in_list(expr: Expr, list: Expr) -> Expr {
+    let values = list.clone().explode();
+    let unknown = expr
+        .clone()
+        .is_null()
+        .and(values.clone().len().gt(lit(0)))
+        .or(values.null_count().gt(lit(0)));
+    when(expr.is_in(list, false))
+        .then(lit(true))
+        .when(unknown)
+        .then(lit(NULL))
+        .otherwise(lit(false))
+}
+
+/// Convert SqlParser BinaryOperator To DataFrame Operator
+impl TryFrom<InterimOperator> for Operator {
+    type Error = CustomError;
+
+    fn try_from(op: InterimOperator) -> std::result::Result<Self, Self::Error> {
+        match op.0 {
+            SqlBinaryOperator::Plus => Ok(Self::Plus),
+            SqlBinaryOperator::Minus => Ok(Self::Minus),
+            SqlBinaryOperator::Multiply => Ok(Self::Multiply),
+            SqlBinaryOperator::Divide => Ok(Self::Divide),
+            SqlBinaryOperator::Modulo => Ok(Self::Modulus),
+            SqlBinaryOperator::Gt => Ok(Self::Gt),
+            SqlBinaryOperator::Lt => Ok(Self::Lt),
+            SqlBinaryOperator::GtEq => Ok(Self::GtEq),
+            SqlBinaryOperator::LtEq => Ok(Self::LtEq),
+            SqlBinaryOperator::Eq => Ok(Self::Eq),
+            SqlBinaryOperator::NotEq => Ok(Self::NotEq),
+            SqlBinaryOperator::And => Ok(Self::And),
+            SqlBinaryOperator::Or => Ok(Self::Or),
+            v => Err(CustomError::SqlOperatorError(v.to_string())),
+        }
+    }
+}
+
+/// Convert SqlParser SelectItem to Expr of polars
+impl<'a> TryFrom<InterimSelectItem<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(p: InterimSelectItem<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimSelectItem(item, subqueries) = p;
+        match item {
+            // the quotes of identifier are not part of the name, example: "Sepal Length"
+            SelectItem::UnnamedExpr(SqlExpr::Identifier(id)) => Ok(col(id.value.as_str())),
+            SelectItem::UnnamedExpr(SqlExpr::CompoundIdentifier(idents)) => {
+                Ok(col(qualified_name(idents)))
+            }
+            SelectItem::UnnamedExpr(expr) => {
+                let temp_expr: Expr = InterimExpr(expr, subqueries).try_into()?;
+                // Computed and aggregation columns are named after the expression text,
+                // example: max(x)
+                Ok(Expr::Alias(Arc::new(temp_expr), expr.to_string().into()))
+            }
+            SelectItem::ExprWithAlias { expr, alias } => {
+                let temp_expr: Expr = InterimExpr(expr, subqueries).try_into()?;
+                Ok(Expr::Alias(
+                    Arc::new(temp_expr),
+                    alias.value.to_owned().into(),
+                ))
+            }
+            SelectItem::Wildcard(wildcard_options) => {
+                let token_with_span = wildcard_options.wildcard_token.0.clone();
+                let token = token_with_span.token;
+                match token {
+                    Token::Mul => Ok(Expr::Wildcard),
+                    _ => Err(CustomError::SqlSelectItemError(token.to_string())),
+                }
+            }
+            item => Err(CustomError::SqlSelectItemError(item.to_string())),
+        }
+    }
+}
+
+/// Convert SqlParser function to Expr of polars, functions with over clause are window functions
+impl<'a> TryFrom<InterimFuncExprItem<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(v: InterimFuncExprItem<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimFuncExprItem(func, subqueries) = v;
+        match &func.over {
+            Some(WindowType::WindowSpec(spec)) => InterimWindow(func, spec, subqueries).try_into(),
+            Some(WindowType::NamedWindow(name)) => Err(CustomError::SqlWindowError(format!(
+                "{} over named window {}",
+                func.name, name
+            ))),
+            None => match ScalarFunc::try_from(func.name.to_string().as_str()) {
+                Ok(scalar_func) => InterimScalarFunc(func, scalar_func, subqueries).try_into(),
+                Err(_) => InterimAggregation(func, subqueries).try_into(),
+            },
+        }
+    }
+}
+
+/// Convert SqlParser scalar function to Expr of polars, conditions are when/then/otherwise
+impl<'a> TryFrom<InterimScalarFunc<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(v: InterimScalarFunc<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimScalarFunc(Function { name, args, .. }, scalar_func, subqueries) = v;
+
+        // interval of months is not fixed, so it offsets the calendar,
+        // example: date_add(day, INTERVAL '1 month')
+        if let (ScalarFunc::DateAdd | ScalarFunc::DateSub, FunctionArguments::List(list)) =
+            (scalar_func, args)
+            && let [
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(value)),
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(SqlExpr::Interval(interval))),
+            ] = list.args.as_slice()
+        {
+            let value: Expr = InterimExpr(value, subqueries).try_into()?;
+            let duration: Duration = InterimInterval(interval).try_into()?;
+            let duration = if scalar_func == ScalarFunc::DateSub {
+                -duration
+            } else {
+                duration
+            };
+            return Ok(to_temporal(value).dt().offset_by(lit(duration.to_string())));
+        }
+
+        let mut arguments: Vec<Expr> = InterimFuncArgsExprItem(args, subqueries).try_into()?;
+        let (min, max) = scalar_func.arity();
+        if arguments.len() < min || arguments.len() > max {
+            return Err(CustomError::SqlExprFuncArgsItem(format!(
+                "{}{} arguments",
+                name, args
+            )));
+        }
+
+        match scalar_func {
+            ScalarFunc::Coalesce => Ok(coalesce(&arguments)),
+            ScalarFunc::NullIf => {
+                let value = arguments.remove(0);
+                Ok(when(value.clone().eq(arguments.remove(0)))
+                    .then(lit(NULL))
+                    .otherwise(value))
+            }
+            ScalarFunc::IfNull => Ok(coalesce(&arguments)),
+            ScalarFunc::If => {
+                let condition = arguments.remove(0);
+                Ok(when(condition)
+                    .then(arguments.remove(0))
+                    .otherwise(arguments.remove(0)))
+            }
+            // nulls are skipped, the result is null only when all arguments are null
+            ScalarFunc::Greatest | ScalarFunc::Least => Ok(arguments
+                .into_iter()
+                .reduce(|acc, e| {
+                    let kept = if scalar_func == ScalarFunc::Greatest {
+                        acc.clone().gt_eq(e.clone())
+                    } else {
+                        acc.clone().lt_eq(e.clone())
+                    };
+                    when(e.clone().is_null().or(kept)).then(acc).otherwise(e)
+                })
+                .unwrap_or(lit(NULL))),
+            ScalarFunc::Lower => Ok(arguments.remove(0).str().to_lowercase()),
+            ScalarFunc::Upper => Ok(arguments.remove(0).str().to_uppercase()),
+            // whitespace is trimmed when the characters are not given
+            ScalarFunc::Ltrim => {
+                let value = arguments.remove(0);
+                Ok(value
+                    .str()
+                    .strip_chars_start(arguments.pop().unwrap_or(lit(NULL))))
+            }
+            ScalarFunc::Rtrim => {
+                let value = arguments.remove(0);
+                Ok(value
+                    .str()
+                    .strip_chars_end(arguments.pop().unwrap_or(lit(NULL))))
+            }
+            ScalarFunc::Length => Ok(arguments.remove(0).str().len_chars()),
+            ScalarFunc::Substr => {
+                let value = arguments.remove(0);
+                let start = arguments.remove(0);
+                Ok(substring(value, Some(start), arguments.pop()))
+            }
+            ScalarFunc::Replace => {
+                let value = arguments.remove(0);
+                let pattern = arguments.remove(0);
+                Ok(value.str().replace_all(pattern, arguments.remove(0), true))
+            }
+            // nulls are ignored, the same as postgres
+            ScalarFunc::Concat => Ok(concat_str(arguments, "", true)),
+            // index starts from 1 and negative one counts from the end, the part out of
+            // range is null while index 0 is an error, the same as postgres
+            ScalarFunc::SplitPart => {
+                let value = arguments.remove(0);
+                let delimiter = arguments.remove(0);
+                let index = match arguments.remove(0) {
+                    index if literal_int(&index) == Some(0) => {
+                        return Err(CustomError::SqlExprFuncArgsItem(format!(
+                            "{}{} index",
+                            name, args
+                        )));
+                    }
+                    index if literal_int(&index).is_some() => index,
+                    index => index.map(
+                        |column| {
+                            if column.as_materialized_series().equal(0)?.any() {
+                                return Err(PolarsError::ComputeError(
+                                    "split_part index can not be 0".into(),
+                                ));
+                            }
+                            Ok(Some(column))
+                        },
+                        GetOutput::same_type(),
+                    ),
+                };
+                let index = when(index.clone().gt(lit(0)))
+                    .then(index.clone() - lit(1))
+                    .otherwise(index);
+                Ok(value.str().split(delimiter).list().get(index, true))
+            }
+            ScalarFunc::StartsWith => {
+                let value = arguments.remove(0);
+                Ok(value.str().starts_with(arguments.remove(0)))
+            }
+            ScalarFunc::EndsWith => {
+                let value = arguments.remove(0);
+                Ok(value.str().ends_with(arguments.remove(0)))
+            }
+            ScalarFunc::Contains => {
+                let value = arguments.remove(0);
+                Ok(value.str().contains_literal(arguments.remove(0)))
+            }
+            // the fill is repeated up to the length and the string longer than length is
+            // truncated, example: lpad(code, 5, '0')
+            ScalarFunc::Lpad | ScalarFunc::Rpad => {
+                let value = arguments.remove(0);
+                let length = literal_int(&arguments.remove(0))
+                    .and_then(|v| usize::try_from(v).ok())
+                    .ok_or_else(|| {
+                        CustomError::SqlExprFuncArgsItem(format!("{}{} length", name, args))
+                    })?;
+                let fill = match arguments.pop() {
+                    Some(fill) => literal_str(&fill).ok_or_else(|| {
+                        CustomError::SqlExprFuncArgsItem(format!("{}{} fill", name, args))
+                    })?,
+                    None => " ".to_string(),
+                };
+                let fill = lit(fill.chars().cycle().take(length).collect::<String>());
+                let padded = if scalar_func == ScalarFunc::Lpad {
+                    let missing =
+                        lit(length as i64) - value.clone().str().len_chars().cast(DataType::Int64);
+                    let missing = when(missing.clone().gt(lit(0)))
+                        .then(missing)
+                        .otherwise(lit(0));
+                    fill.str().head(missing) + value
+                } else {
+                    value + fill
+                };
+                Ok(padded.str().head(lit(length as u64)))
+            }
+            // only the first match is replaced unless the flags contain `g`
+            ScalarFunc::RegexpReplace => {
+                let value = arguments.remove(0);
+                let pattern = arguments.remove(0);
+                let replacement = arguments.remove(0);
+                let flags = match arguments.pop() {
+                    Some(flags) => literal_str(&flags).ok_or_else(|| {
+                        CustomError::SqlExprFuncArgsItem(format!("{}{} flags", name, args))
+                    })?,
+                    None => String::new(),
+                };
+                let pattern = if flags.contains('i') {
+                    lit("(?i)") + pattern
+                } else {
+                    pattern
+                };
+                Ok(if flags.contains('g') {
+                    value.str().replace_all(pattern, replacement, false)
+                } else {
+                    value.str().replace(pattern, replacement, false)
+                })
+            }
+            // the whole match is extracted when the group is not given
+            ScalarFunc::RegexpExtract => {
+                let value = arguments.remove(0);
+                let pattern = arguments.remove(0);
+                let group = match arguments.pop() {
+                    Some(group) => literal_int(&group)
+                        .and_then(|v| usize::try_from(v).ok())
+                        .ok_or_else(|| {
+                            CustomError::SqlExprFuncArgsItem(format!("{}{} group", name, args))
+                        })?,
+                    None => 0,
+                };
+                Ok(value.str().extract(pattern, group))
+            }
+            ScalarFunc::RegexpLike => {
+                let value = arguments.remove(0);
+                Ok(value.str().contains(arguments.remove(0), true))
+            }
+            // example: date_trunc('month', day)
+            ScalarFunc::DateTrunc => {
+                let every = literal_str(&arguments.remove(0))
+                    .and_then(|unit| date_every(&unit))
+                    .ok_or_else(|| {
+                        CustomError::SqlExprFuncArgsItem(format!("{}{} unit", name, args))
+                    })?;
+                Ok(to_temporal(arguments.remove(0)).dt().truncate(lit(every)))
+            }
+            ScalarFunc::DatePart => {
+                let part = literal_str(&arguments.remove(0)).unwrap_or_default();
+                date_part(&part, arguments.remove(0)).ok_or_else(|| {
+                    CustomError::SqlExprFuncArgsItem(format!("{}{} part", name, args))
+                })
+            }
+            // format is the specifier of strftime, example: strftime(day, '%Y-%m')
+            ScalarFunc::Strftime => {
+                let value = arguments.remove(0);
+                let format = literal_str(&arguments.remove(0)).ok_or_else(|| {
+                    CustomError::SqlExprFuncArgsItem(format!("{}{} format", name, args))
+                })?;
+                Ok(to_temporal(value).dt().strftime(&format))
+            }
+            ScalarFunc::Strptime | ScalarFunc::ToDate | ScalarFunc::ToTimestamp => {
+                let value = arguments.remove(0);
+                let format = match arguments.pop() {
+                    Some(format) => Some(literal_str(&format).ok_or_else(|| {
+                        CustomError::SqlExprFuncArgsItem(format!("{}{} format", name, args))
+                    })?),
+                    // to_timestamp of number converts the seconds since epoch
+                    None if scalar_func == ScalarFunc::ToTimestamp => {
+                        return Ok((value * lit(1_000_000))
+                            .cast(DataType::Int64)
+                            .cast(DataType::Datetime(TimeUnit::Microseconds, None)));
+                    }
+                    None => None,
+                };
+                let options = StrptimeOptions {
+                    format: format.map(|format| format.into()),
+                    ..Default::default()
+                };
+                Ok(if scalar_func == ScalarFunc::ToDate {
+                    value.str().to_date(options)
+                } else {
+                    value.str().to_datetime(
+                        Some(TimeUnit::Microseconds),
+                        None,
+                        options,
+                        lit("raise"),
+                    )
+                })
+            }
+            // the time is taken when the query is converted
+            ScalarFunc::Now | ScalarFunc::CurrentDate => {
+                let micros = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_micros() as i64)
+                    .unwrap_or_default();
+                Ok(if scalar_func == ScalarFunc::Now {
+                    lit(Scalar::new_datetime(micros, TimeUnit::Microseconds, None))
+                } else {
+                    lit(Scalar::new_date((micros / 86_400_000_000) as i32))
+                })
+            }
+            ScalarFunc::DateAdd | ScalarFunc::DateSub => {
+                let value = to_temporal(arguments.remove(0));
+                let duration = arguments.remove(0);
+                Ok(if scalar_func == ScalarFunc::DateAdd {
+                    value + duration
+                } else {
+                    value - duration
+                })
+            }
+            // number of unit boundaries between start and end, example: date_diff('day', a, b)
+            ScalarFunc::DateDiff => {
+                let unit = literal_str(&arguments.remove(0)).unwrap_or_default();
+                let start = to_temporal(arguments.remove(0));
+                let end = to_temporal(arguments.remove(0));
+                date_diff(&unit, start, end).ok_or_else(|| {
+                    CustomError::SqlExprFuncArgsItem(format!("{}{} unit", name, args))
+                })
+            }
+            ScalarFunc::Epoch => date_part("epoch", arguments.remove(0))
+                .ok_or_else(|| CustomError::SqlExpressionError(format!("{}{}", name, args))),
+            ScalarFunc::Abs => Ok(arguments.remove(0).abs()),
+            // half is rounded away from zero, example: round(price, 2)
+            ScalarFunc::Round => {
+                let value = arguments.remove(0);
+                let decimals = match arguments.pop() {
+                    Some(decimals) => literal_int(&decimals)
+                        .and_then(|v| u32::try_from(v).ok())
+                        .ok_or_else(|| {
+                            CustomError::SqlExprFuncArgsItem(format!("{}{} decimals", name, args))
+                        })?,
+                    None => 0,
+                };
+                Ok(value.round(decimals, RoundMode::HalfAwayFromZero))
+            }
+            ScalarFunc::Ceil => Ok(arguments.remove(0).ceil()),
+            ScalarFunc::Floor => Ok(arguments.remove(0).floor()),
+            ScalarFunc::Sqrt => Ok(arguments.remove(0).sqrt()),
+            ScalarFunc::Cbrt => Ok(arguments.remove(0).cbrt()),
+            ScalarFunc::Power => {
+                let value = arguments.remove(0);
+                Ok(value.cast(DataType::Float64).pow(arguments.remove(0)))
+            }
+            ScalarFunc::Ln => Ok(arguments.remove(0).log(std::f64::consts::E)),
+            // log of single argument is base 10, example: log(2, x) is base 2
+            ScalarFunc::Log => {
+                let value = arguments
+                    .pop()
+                    .ok_or_else(|| CustomError::SqlExpressionError(format!("{}{}", name, args)))?;
+                Ok(match arguments.pop() {
+                    Some(base) => value.log(std::f64::consts::E) / base.log(std::f64::consts::E),
+                    None => value.log(10.0),
+                })
+            }
+            ScalarFunc::Log2 => Ok(arguments.remove(0).log(2.0)),
+            ScalarFunc::Log10 => Ok(arguments.remove(0).log(10.0)),
+            ScalarFunc::Exp => Ok(arguments.remove(0).exp()),
+            ScalarFunc::Sign => Ok(arguments.remove(0).sign()),
+            ScalarFunc::Sin => Ok(arguments.remove(0).sin()),
+            ScalarFunc::Cos => Ok(arguments.remove(0).cos()),
+            ScalarFunc::Tan => Ok(arguments.remove(0).tan()),
+            ScalarFunc::Cot => Ok(arguments.remove(0).cot()),
+            ScalarFunc::Asin => Ok(arguments.remove(0).arcsin()),
+            ScalarFunc::Acos => Ok(arguments.remove(0).arccos()),
+            ScalarFunc::Atan => Ok(arguments.remove(0).arctan()),
+            ScalarFunc::Atan2 => {
+                let y = arguments.remove(0);
+                Ok(y.arctan2(arguments.remove(0)))
+            }
+            ScalarFunc::Degrees => Ok(arguments.remove(0).degrees()),
+            ScalarFunc::Radians => Ok(arguments.remove(0).radians()),
+            ScalarFunc::Pi => Ok(lit(std::f64::consts::PI)),
+            // remainder takes the sign of the dividend, example: mod(-7, 2) is -1
+            ScalarFunc::Mod => {
+                let value = arguments.remove(0);
+                let divisor = arguments.remove(0);
+                Ok(value.clone() - divisor.clone() * truncated_div(value, divisor))
+            }
+            // integer division rounds towards zero, example: div(-7, 2) is -3
+            ScalarFunc::Div => {
+                let value = arguments.remove(0);
+                Ok(truncated_div(value, arguments.remove(0)))
+            }
+        }
+    }
+}
+
+/// Compare with date or timestamp literal as timestamp, so that iso date string is parsed,
+/// example: last_updated_date > DATE '2024-08-01'
+fn coerce_temporal(left: Expr, right: Expr) -> (Expr, Expr) {
+    let is_temporal = |expr: &Expr| {
+        matches!(expr, Expr::Literal(LiteralValue::Scalar(scalar))
+            if matches!(scalar.dtype(), DataType::Date | DataType::Datetime(_, _)))
+    };
+    if is_temporal(&left) || is_temporal(&right) {
+        (to_temporal(left), to_temporal(right))
+    } else {
+        (left, right)
+    }
+}
+
+/// Parse value to timestamp, iso string of date or timestamp and date are converted
+fn to_temporal(value: Expr) -> Expr {
+    let timestamp = DataType::Datetime(TimeUnit::Microseconds, None);
+    coalesce(&[
+        value.clone().cast(timestamp.clone()),
+        value.cast(DataType::Date).cast(timestamp),
+    ])
+}
+
+/// Truncation interval of date unit, example: month is 1mo
+fn date_every(unit: &str) -> Option<&'static str> {
+    match unit.to_lowercase().as_str() {
+        "year" => Some("1y"),
+        "quarter" => Some("1q"),
+        "month" => Some("1mo"),
+        "week" => Some("1w"),
+        "day" => Some("1d"),
+        "hour" => Some("1h"),
+        "minute" => Some("1m"),
+        "second" => Some("1s"),
+        "millisecond" => Some("1ms"),
+        _ => None,
+    }
+}
+
+/// Take the part of date, day of week starts from sunday as 0 and epoch is the seconds
+/// since 1970-01-01
+fn date_part(part: &str, value: Expr) -> Option<Expr> {
+    let value = to_temporal(value).dt();
+    match part.to_lowercase().as_str() {
+        "year" | "years" => Some(value.year()),
+        "quarter" => Some(value.quarter()),
+        "month" | "months" => Some(value.month()),
+        "week" | "isoweek" => Some(value.week()),
+        "day" | "days" => Some(value.day()),
+        "dow" | "dayofweek" => Some(value.weekday() % lit(7)),
+        "isodow" => Some(value.weekday()),
+        "doy" | "dayofyear" => Some(value.ordinal_day()),
+        "hour" | "hours" => Some(value.hour()),
+        "minute" | "minutes" => Some(value.minute()),
+        "second" | "seconds" => Some(value.second()),
+        "millisecond" | "milliseconds" => Some(value.millisecond()),
+        "microsecond" | "microseconds" => Some(value.microsecond()),
+        "epoch" => Some(
+            value
+                .timestamp(TimeUnit::Microseconds)
+                .cast(DataType::Float64)
+                / lit(1_000_000.0),
+        ),
+        _ => None,
+    }
+}
+
+/// Count the unit boundaries between start and end
+fn date_diff(unit: &str, start: Expr, end: Expr) -> Option<Expr> {
+    let part =
+        |part: &str, value: &Expr| date_part(part, value.clone()).map(|v| v.cast(DataType::Int64));
+    let years = part("year", &end)? - part("year", &start)?;
+    let unit = unit.to_lowercase();
+    match unit.as_str() {
+        "year" => Some(years),
+        "quarter" => Some(years * lit(4) + part("quarter", &end)? - part("quarter", &start)?),
+        "month" => Some(years * lit(12) + part("month", &end)? - part("month", &start)?),
+        _ => {
+            let every = date_every(&unit)?;
+            let duration = end.dt().truncate(lit(every)) - start.dt().truncate(lit(every));
+            match unit.as_str() {
+                "week" => Some(duration.dt().total_days() / lit(7)),
+                "day" => Some(duration.dt().total_days()),
+                "hour" => Some(duration.dt().total_hours()),
+                "minute" => Some(duration.dt().total_minutes()),
+                "second" => Some(duration.dt().total_seconds()),
+                _ => Some(duration.dt().total_milliseconds()),
+            }
+        }
+    }
+}
+
+/// Take the integer of literal expression, example: lpad(code, 5, '0')
+fn literal_int(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Int(v))) => Some(*v),
+        _ => None,
+    }
+}
+
+/// Take the string of literal expression
+fn literal_str(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Str(v))) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+/// Divide and round the quotient towards zero, the floor of an inexact quotient is one
+/// below it when the signs of operands differ
+fn truncated_div(value: Expr, divisor: Expr) -> Expr {
+    let quotient = value.clone().floor_div(divisor.clone());
+    let inexact = (value.clone() - divisor.clone() * quotient.clone()).neq(lit(0));
+    let signs = value.lt(lit(0)).neq(divisor.lt(lit(0)));
+    when(inexact.and(signs))
+        .then(quotient.clone() + lit(1))
+        .otherwise(quotient)
+}
+
+/// Take the part of string by 1-based start and length, the rest of string is taken when
+/// the length is not given. A start before the first character is moved to it and the
+/// length is reduced by the same, example: substr('setosa', 0, 3) is 'se'
+fn substring(value: Expr, start: Option<Expr>, length: Option<Expr>) -> Expr {
+    let (start, length) = match start {
+        None => (lit(0), length),
+        Some(start) if literal_int(&start).is_some_and(|v| v >= 1) => (start - lit(1), length),
+        Some(start) => {
+            let first = when(start.clone().lt(lit(1)))
+                .then(lit(1))
+                .otherwise(start.clone());
+            let length = length.map(|length| {
+                let rest = start + length - first.clone();
+                when(rest.clone().lt(lit(0))).then(lit(0)).otherwise(rest)
+            });
+            (first - lit(1), length)
+        }
+    };
+    value.str().slice(start, length.unwrap_or(lit(NULL)))
+}
+
+impl<'a> TryFrom<InterimAggregation<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(v: InterimAggregation<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimAggregation(
+            Function {
+                name,
+                args,
+                within_group,
+                ..
+            },
+            subqueries,
+        ) = v;
+
+        // get aggregation func name
+        let agg_func = if let ObjectNamePart::Identifier(ident) = &name.0[0] {
+            AggFunc::from_str(&ident.value).ok_or_else(|| {
+                CustomError::SqlExprFuncItem(format!(
+                    "Unsupported aggregation function: {}",
+                    ident.value
+                ))
+            })?
+        } else {
+            return Err(CustomError::SqlExprFuncItem(format!(
+                "Invalid aggregation function name ({:?})",
+                &name.0
+            )));
+        };
+
+        // get argument expressions, `*` is only allowed by count
+        let mut arguments: Vec<Expr> = InterimFuncArgsExprItem(args, subqueries).try_into()?;
+        if arguments.len() != agg_func.arity() {
+            return Err(CustomError::SqlExprFuncArgsItem(format!(
+                "{}{} requires {} arguments",
+                name,
+                args,
+                agg_func.arity()
+            )));
+        }
+        let arg = arguments.remove(0);
+        let wildcard = matches!(arg, Expr::Wildcard);
+        if wildcard && agg_func != AggFunc::Count {
+            return Err(CustomError::SqlExprFuncArgsItem(format!("{}(*)", name)));
+        }
+
+        // ordered-set aggregation takes the column from within group,
+        // example: percentile_cont(0.9) within group (order by x)
+        let within = match (agg_func, within_group.as_slice()) {
+            (AggFunc::PercentileCont, [order_by]) => {
+                let expr: Expr = InterimExpr(&order_by.expr, subqueries).try_into()?;
+                Some((expr, order_by.options.asc == Some(false)))
+            }
+            (_, []) => None,
+            _ => {
+                return Err(CustomError::SqlExprFuncArgsItem(format!(
+                    "{} within group {:?}",
+                    name, within_group
+                )));
+            }
+        };
+
+        // distinct aggregation only takes unique values, nulls are ignored like other aggregations
+        let distinct = matches!(
+            args,
+            FunctionArguments::List(FunctionArgumentList {
+                duplicate_treatment: Some(DuplicateTreatment::Distinct),
+                ..
+            })
+        );
+        let column = if distinct {
+            arg.clone().drop_nulls().unique()
+        } else {
+            arg.clone()
+        };
+
+        // generate aggregation
+        match agg_func {
+            AggFunc::Max => Ok(column.max()),
+            AggFunc::Min => Ok(column.min()),
+            AggFunc::Sum => Ok(column.sum()),
+            AggFunc::Avg => Ok(column.mean()),
+            AggFunc::Count if wildcard && distinct => Err(CustomError::SqlExprFuncArgsItem(
+                "count(distinct *)".to_string(),
+            )),
+            AggFunc::Count if wildcard => Ok(len()),
+            AggFunc::Count if distinct => Ok(arg.drop_nulls().n_unique()),
+            AggFunc::Count => Ok(column.count()),
+            AggFunc::Median => Ok(column.median()),
+            AggFunc::StddevSamp => Ok(column.std(1)),
+            AggFunc::StddevPop => Ok(column.std(0)),
+            AggFunc::VarSamp => Ok(column.var(1)),
+            AggFunc::VarPop => Ok(column.var(0)),
+            AggFunc::PercentileCont => match within {
+                // descending order takes the percentile from the other end
+                Some((column, true)) => Ok(column.quantile(lit(1.0) - arg, QuantileMethod::Linear)),
+                Some((column, false)) => Ok(column.quantile(arg, QuantileMethod::Linear)),
+                None => Err(CustomError::SqlExprFuncArgsItem(format!(
+                    "{}{} requires within group",
+                    name, args
+                ))),
+            },
+            AggFunc::ApproxCountDistinct => Ok(arg.drop_nulls().approx_n_unique()),
+            AggFunc::First => Ok(column.first()),
+            AggFunc::Last => Ok(column.last()),
+            AggFunc::ArrayAgg => Ok(column.implode()),
+            AggFunc::StringAgg => match &arguments[0] {
+                Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Str(separator))) => {
+                    Ok(column.cast(DataType::String).str().join(separator, true))
+                }
+                v => Err(CustomError::SqlExprFuncArgsItem(format!(
+                    "string_agg separator {}",
+                    v
+                ))),
+            },
+        }
+    }
+}
+
+/// Rows of window frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    // the whole partition
+    Partition,
+    // from the start of partition to the current row
+    Cumulative,
+    // from the start of partition to the last peer of the current row
+    Peers,
+    // the preceding rows and the current row
+    Preceding(usize),
+}
+
+/// Convert SqlParser window spec to frame, the frame of ordered window ends at the last peer
+/// of the current row by default
+impl<'a> TryFrom<InterimWindowFrame<'a>> for Frame {
+    type Error = CustomError;
+
+    fn try_from(spec: InterimWindowFrame<'a>) -> Result<Self, Self::Error> {
+        let Some(frame) = &spec.0.window_frame else {
+            return Ok(if spec.0.order_by.is_empty() {
+                Frame::Partition
+            } else {
+                Frame::Peers
+            });
+        };
+
+        let end_bound = frame
+            .end_bound
+            .as_ref()
+            .unwrap_or(&WindowFrameBound::CurrentRow);
+        match (&frame.units, &frame.start_bound, end_bound) {
+            (_, WindowFrameBound::Preceding(None), WindowFrameBound::Following(None)) => {
+                Ok(Frame::Partition)
+            }
+            (
+                WindowFrameUnits::Rows,
+                WindowFrameBound::Preceding(None),
+                WindowFrameBound::CurrentRow,
+            ) => Ok(Frame::Cumulative),
+            (_, WindowFrameBound::Preceding(None), WindowFrameBound::CurrentRow) => {
+                Ok(Frame::Peers)
+            }
+            (
+                WindowFrameUnits::Rows,
+                WindowFrameBound::CurrentRow,
+                WindowFrameBound::CurrentRow,
+            ) => Ok(Frame::Preceding(0)),
+            (
+                WindowFrameUnits::Rows,
+                WindowFrameBound::Preceding(Some(rows)),
+                WindowFrameBound::CurrentRow,
+            ) => match rows.as_ref() {
+                SqlExpr::Value(ValueWithSpan {
+                    value: SqlValue::Number(v, _),
+                    ..
+                }) if v.parse::<usize>().is_ok() => Ok(Frame::Preceding(v.parse().unwrap_or(0))),
+                v => Err(CustomError::SqlWindowError(format!("{} PRECEDING", v))),
+            },
+            (units, start_bound, end_bound) => Err(CustomError::SqlWindowError(format!(
+                "{} BETWEEN {} AND {}",
+                units, start_bound, end_bound
+            ))),
+        }
+    }
+}
+
+/// Convert SqlParser window function to window expression of polars, the function is computed
+/// on each partition sorted by the order of window
+impl<'a> TryFrom<InterimWindow<'a, '_>> for Expr {
+    type Error = CustomError;
+
+    fn try_from(window: InterimWindow<'a, '_>) -> Result<Self, Self::Error> {
+        let InterimWindow(func, spec, subqueries) = window;
+
+        // partition and order of window, the keys share one direction
+        let mut partition_by = Vec::new();
+        for expr in &spec.partition_by {
+            partition_by.push(InterimExpr(expr, subqueries).try_into()?);
+        }
+        let mut order_by: Vec<Expr> = Vec::new();
+        let mut directions = Vec::new();
+        for order in &spec.order_by {
+            order_by.push(InterimExpr(&order.expr, subqueries).try_into()?);
+            // nulls come last in ascending order by default, the same as the order of query
+            let descending = order.options.asc == Some(false);
+            directions.push((descending, order.options.nulls_first.unwrap_or(descending)));
+        }
+        let (descending, nulls_first) = directions.first().copied().unwrap_or((false, false));
+        if directions.iter().any(|v| *v != (descending, nulls_first)) {
+            return Err(CustomError::SqlWindowError(format!(
+                "{} with mixed order directions",
+                spec
+            )));
+        }
+        let frame: Frame = InterimWindowFrame(spec).try_into()?;
+
+        let name = func.name.to_string().to_lowercase();
+        let mut arguments: Vec<Expr> =
+            InterimFuncArgsExprItem(&func.args, subqueries).try_into()?;
+        let arity_error =
+            || CustomError::SqlWindowError(format!("{}{} arguments", func.name, func.args));
+        let row_number = int_range(lit(1), len() + lit(1), 1, IDX_DTYPE);
+        let key = match order_by.as_slice() {
+            [] => None,
+            [key] => Some(key.clone()),
+            keys => Some(as_struct(keys.to_vec())),
+        };
+        // rows of the same order are peers sharing the frame ending at the last of them, the
+        // last peer of sorted partition is at the maximum rank, after the nulls coming first
+        let last_peer = |expr: Expr| match &key {
+            Some(key) => {
+                let rank = key.clone().rank(
+                    RankOptions {
+                        method: RankMethod::Max,
+                        descending,
+                    },
+                    None,
+                );
+                let position = if nulls_first {
+                    rank.fill_null(lit(0)) + key.clone().null_count()
+                } else {
+                    rank.fill_null(len())
+                };
+                expr.gather(position - lit(1))
+            }
+            None => expr.last(),
+        };
+        let expr = match name.as_str() {
+            "row_number" if arguments.is_empty() => row_number,
+            "rank" | "dense_rank" if arguments.is_empty() => {
+                let Some(key) = key.clone() else {
+                    return Err(CustomError::SqlWindowError(format!(
+                        "{} without order",
+                        name
+                    )));
+                };
+                let method = if name == "rank" {
+                    RankMethod::Min
+                } else {
+                    RankMethod::Dense
+                };
+                key.rank(RankOptions { method, descending }, None)
+            }
+            // rows are distributed to buckets as evenly as possible, leading buckets take the rest
+            "ntile" if arguments.len() == 1 => {
+                let buckets = arguments.remove(0).cast(DataType::Int64);
+                let count = len().cast(DataType::Int64);
+                let index = row_number.cast(DataType::Int64) - lit(1);
+                let size = count.clone().floor_div(buckets.clone());
+                let rest = count - size.clone() * buckets;
+                let boundary = rest.clone() * (size.clone() + lit(1));
+                when(index.clone().lt(boundary.clone()))
+                    .then(index.clone().floor_div(size.clone() + lit(1)))
+                    .otherwise((index - boundary).floor_div(size) + rest)
+                    + lit(1)
+            }
+            "lag" | "lead" if (1..=3).contains(&arguments.len()) => {
+                let mut arguments = arguments.into_iter();
+                let value = arguments.next().ok_or_else(arity_error)?;
+                let offset = arguments.next().unwrap_or(lit(1)).cast(DataType::Int64);
+                let offset = if name == "lag" {
+                    offset
+                } else {
+                    lit(0) - offset
+                };
+                match arguments.next() {
+                    Some(default) => value.shift_and_fill(offset, default),
+                    None => value.shift(offset),
+                }
+            }
+            // frame ending at the current row takes the value of the row as the last value
+            "first_value" if arguments.len() == 1 => match frame {
+                Frame::Partition | Frame::Cumulative | Frame::Peers => arguments.remove(0).first(),
+                Frame::Preceding(_) => {
+                    return Err(CustomError::SqlWindowError(format!("{} frame", name)));
+                }
+            },
+            "last_value" if arguments.len() == 1 => match frame {
+                Frame::Partition => arguments.remove(0).last(),
+                Frame::Peers => last_peer(arguments.remove(0)),
+                Frame::Cumulative | Frame::Preceding(_) => arguments.remove(0),
+            },
+            "row_number" | "rank" | "dense_rank" | "ntile" | "lag" | "lead" | "first_value"
+            | "last_value" => return Err(arity_error()),
+            // aggregation over the rows of frame
+            _ => {
+                let agg_func = AggFunc::from_str(&name).ok_or_else(|| {
+                    CustomError::SqlExprFuncItem(format!(
+                        "Unsupported aggregation function: {}",
+                        name
+                    ))
+                })?;
+                if let FunctionArguments::List(FunctionArgumentList {
+                    duplicate_treatment: Some(DuplicateTreatment::Distinct),
+                    ..
+                }) = &func.args
+                {
+                    return Err(CustomError::SqlWindowError(format!("{} distinct", name)));
+                }
+                let value = match arguments.first() {
+                    Some(Expr::Wildcard) => row_number,
+                    Some(expr) if arguments.len() == 1 => expr.clone(),
+                    _ => return Err(arity_error()),
+                };
+                let options = |rows: usize| RollingOptionsFixedWindow {
+                    window_size: rows + 1,
+                    min_periods: 1,
+                    ..Default::default()
+                };
+                let expr = match (frame, agg_func) {
+                    (Frame::Partition, _) => InterimAggregation(func, subqueries).try_into()?,
+                    (Frame::Cumulative | Frame::Peers, AggFunc::Sum) => value.cum_sum(false),
+                    (Frame::Cumulative | Frame::Peers, AggFunc::Count) => value.cum_count(false),
+                    (Frame::Cumulative | Frame::Peers, AggFunc::Min) => value.cum_min(false),
+                    (Frame::Cumulative | Frame::Peers, AggFunc::Max) => value.cum_max(false),
+                    (Frame::Cumulative | Frame::Peers, AggFunc::Avg) => {
+                        value.clone().cum_sum(false).cast(DataType::Float64)
+                            / value.cum_count(false)
+                    }
+                    (Frame::Preceding(rows), AggFunc::Sum) => value.rolling_sum(options(rows)),
+                    (Frame::Preceding(rows), AggFunc::Count) => value
+                        .is_not_null()
+                        .cast(DataType::UInt32)
+                        .rolling_sum(options(rows)),
+                    (Frame::Preceding(rows), AggFunc::Min) => value.rolling_min(options(rows)),
+                    (Frame::Preceding(rows), AggFunc::Max) => value.rolling_max(options(rows)),
+                    (Frame::Preceding(rows), AggFunc::Avg) => value.rolling_mean(options(rows)),
+                    (frame, agg_func) => {
+                        return Err(CustomError::SqlWindowError(format!(
+                            "{:?} over {:?} frame",
+                            agg_func, frame
+                        )));
+                    }
+                };
+                if frame == Frame::Peers {
+                    last_peer(expr)
+                } else {
+                    expr
+                }
+            }
+        };
+
+        // window without partition takes all rows as one partition
+        if partition_by.is_empty() {
+            partition_by.push(lit(1));
+        }
+        let order_by = if order_by.is_empty() {
+            None
+        } else {
+            Some((
+                order_by,
+                SortOptions::default()
+                    .with_order_descending(descending)
+                    .with_nulls_last(!nulls_first),
+            ))
+        };
+        expr.over_with_options(Some(partition_by), order_by, WindowMapping::GroupsToRows)
+            .map_err(|e| CustomError::SqlWindowError(e.to_string()))
+    }
+}
+
+/// Convert SqlParser function arguments to the argument expressions, `*` is kept as wildcard
+impl<'a> TryFrom<InterimFuncArgsExprItem<'a, '_>> for Vec<Expr> {
+    type Error = CustomError;
+
+    fn try_from(args: InterimFuncArgsExprItem<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimFuncArgsExprItem(args, subqueries) = args;
+        match args {
+            FunctionArguments::List(FunctionArgumentList {
+                args: inner_args,
+                clauses,
+                ..
+            }) if clauses.is_empty() => inner_args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => {
+                        InterimExpr(expr, subqueries).try_into()
+                    }
+                    FunctionArg::Unnamed(FunctionArgExpr::Wildcard) => Ok(Expr::Wildcard),
+                    v => Err(CustomError::SqlExprFuncArgsItem(format!(
+                        "Function requires argument ({})",
+                        v
+                    ))),
+                })
+                .collect(),
+            // function without parentheses, example: current_date
+            FunctionArguments::None => Ok(vec![]),
+            v => Err(CustomError::SqlExprFuncArgsItem(format!(
+                "Function requires arguments ({})",
+                v
+            ))),
+        }
+    }
+}
+
+/// Convert SqlParser from clause to Source, comma separated tables are cross joined
+impl<'a> TryFrom<InterimSource<'a>> for Source<'a> {
+    type Error = CustomError;
+
+    fn try_from(source: InterimSource<'a>) -> Result<Self, Self::Error> {
+        let mut relations = source.0.iter().map(|table| {
+            let mut relation: Source<'a> = InterimTable(&table.relation).try_into()?;
+            for join in &table.joins {
+                let (how, on) = InterimJoin(join).try_into()?;
+                relation = Source::Join {
+                    left: Box::new(relation),
+                    right: Box::new(InterimTable(&join.relation).try_into()?),
+                    how,
+                    on,
+                };
+            }
+            Ok::<_, CustomError>(relation)
+        });
+
+        let first = relations
+            .next()
+            .ok_or_else(|| CustomError::SqlTableError("empty".to_string()))??;
+        relations.try_fold(first, |left, right| {
+            Ok(Source::Join {
+                left: Box::new(left),
+                right: Box::new(right?),
+                how: JoinType::Cross,
+                on: JoinOn::None,
+            })
+        })
+    }
+}
+
+/// Convert SqlParser table factor to Source, the alias names the relation for qualified
+/// columns, example: from http://abc.xyz/a.csv as a where a.b > 1
+impl<'a> TryFrom<InterimTable<'a>> for Source<'a> {
+    type Error = CustomError;
+
+    fn try_from(table: InterimTable<'a>) -> Result<Self, Self::Error> {
+        let (source, alias) = match table.0 {
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } => (InterimTableFunction(name, args).try_into()?, alias),
+            TableFactor::Table { name, alias, .. } => {
+                let [ObjectNamePart::Identifier(ident)] = name.0.as_slice() else {
+                    return Err(CustomError::SqlTableError(name.to_string()));
+                };
+                (Source::Table(&ident.value), alias)
+            }
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => (
+                Source::Query(Box::new(InterimQuery(subquery).try_into()?)),
+                alias,
+            ),
+            v => return Err(CustomError::SqlTableError(format!("{:?}", v))),
+        };
+
+        match alias {
+            Some(alias) if !alias.columns.is_empty() => Err(CustomError::SqlTableError(format!(
+                "{} with column alias",
+                alias
+            ))),
+            Some(alias) => Ok(Source::Alias(Box::new(source), &alias.name.value)),
+            None => Ok(source),
+        }
+    }
+}
+
+/// Convert SqlParser table function to Source read with options, the first argument is
+/// the path and the others are named options, example: read_csv('a.csv', header => false)
+impl<'a> TryFrom<InterimTableFunction<'a>> for Source<'a> {
+    type Error = CustomError;
+
+    fn try_from(f: InterimTableFunction<'a>) -> Result<Self, Self::Error> {
+        let InterimTableFunction(name, args) = f;
+        let format_type = match name.to_string().to_lowercase().as_str() {
+            "read_csv" => FormatType::Csv,
+            "read_json" => FormatType::Json,
+            "read_ndjson" => FormatType::NdJson,
+            "read_parquet" => FormatType::Parquet,
+            _ => return Err(CustomError::SqlTableError(format!("function {}", name))),
+        };
+        let (path, named) = match args.args.as_slice() {
+            [
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(path)),
+                named @ ..,
+            ] if args.settings.is_none() => (path, named),
+            _ => {
+                return Err(CustomError::SqlTableError(format!(
+                    "{} requires a path",
+                    name
+                )));
+            }
+        };
+        let path = match path {
+            SqlExpr::Value(ValueWithSpan {
+                value: SqlValue::SingleQuotedString(path),
+                ..
+            }) => path.as_str(),
+            // unquoted url is an identifier of the dialect
+            SqlExpr::Identifier(ident) => ident.value.as_str(),
+            v => return Err(CustomError::SqlTableError(format!("{} path {}", name, v))),
+        };
+
+        let mut options = LoadOptions::default();
+        for arg in named {
+            let error = || CustomError::SqlTableError(format!("{} option {}", name, arg));
+            let FunctionArg::Named {
+                name: option,
+                arg: FunctionArgExpr::Expr(SqlExpr::Value(ValueWithSpan { value, .. })),
+                ..
+            } = arg
+            else {
+                return Err(error());
+            };
+            let byte = |v: &str| match v.as_bytes() {
+                [b] => Ok(*b),
+                _ => Err(error()),
+            };
+            match (format_type, option.value.to_lowercase().as_str(), value) {
+                (FormatType::Csv, "delimiter", SqlValue::SingleQuotedString(v)) => {
+                    options.delimiter = Some(byte(v)?)
+                }
+                (FormatType::Csv, "quote", SqlValue::SingleQuotedString(v)) => {
+                    options.quote = Some(byte(v)?)
+                }
+                (FormatType::Csv, "header", SqlValue::Boolean(v)) => options.header = Some(*v),
+                (FormatType::Csv, "skip_rows", SqlValue::Number(v, _)) => {
+                    options.skip_rows = Some(v.parse().map_err(|_| error())?)
+                }
+                (
+                    FormatType::Csv | FormatType::Json | FormatType::NdJson,
+                    "infer_schema_length",
+                    SqlValue::Number(v, _),
+                ) => options.infer_schema_length = Some(v.parse().map_err(|_| error())?),
+                _ => return Err(error()),
+            }
+        }
+
+        Ok(Source::Read {
+            path,
+            format_type,
+            options,
+        })
+    }
+}
+
+/// Convert SqlParser join operator to polars join type and join condition
+impl<'a> TryFrom<InterimJoin<'a>> for (JoinType, JoinOn<'a>) {
+    type Error = CustomError;
+
+    fn try_from(join: InterimJoin<'a>) -> Result<Self, Self::Error> {
+        let (how, constraint) = match &join.0.join_operator {
+            JoinOperator::Join(constraint) | JoinOperator::Inner(constraint) => {
+                (JoinType::Inner, constraint)
+            }
+            JoinOperator::Left(constraint) | JoinOperator::LeftOuter(constraint) => {
+                (JoinType::Left, constraint)
+            }
+            JoinOperator::Right(constraint) | JoinOperator::RightOuter(constraint) => {
+                (JoinType::Right, constraint)
+            }
+            JoinOperator::FullOuter(constraint) => (JoinType::Full, constraint),
+            JoinOperator::CrossJoin => (JoinType::Cross, &JoinConstraint::None),
+            v => return Err(CustomError::SqlJoinError(format!("{:?}", v))),
+        };
+
+        let on = match constraint {
+            JoinConstraint::On(expr) => {
+                let mut subqueries = Vec::new();
+                let expr = InterimExpr(expr, &mut subqueries).try_into()?;
+                if !subqueries.is_empty() {
+                    return Err(CustomError::SqlSubqueryError(format!(
+                        "{} in join condition",
+                        expr
+                    )));
+                }
+                JoinOn::Expr(expr)
+            }
+            JoinConstraint::Using(names) => JoinOn::Using(
+                names
+                    .iter()
+                    .map(|name| match name.0.as_slice() {
+                        [ObjectNamePart::Identifier(ident)] => Ok(ident.value.as_str()),
+                        _ => Err(CustomError::SqlJoinError(name.to_string())),
+                    })
+                    .collect::<Result<Vec<&'a str>, CustomError>>()?,
+            ),
+            JoinConstraint::Natural => JoinOn::Natural,
+            JoinConstraint::None if how == JoinType::Cross => JoinOn::None,
+            JoinConstraint::None => {
+                return Err(CustomError::SqlJoinError(format!(
+                    "{} join requires a condition",
+                    how
+                )));
+            }
+        };
+
+        Ok((how, on))
+    }
+}
+
+/// Convert SqlParser order by to key expressions with the direction and the nulls position,
+/// the key is an expression, an ordinal or an alias of the projection, example: order by 2 desc
+impl<'a> TryFrom<InterimOrderBy<'a, '_>> for Vec<(Expr, OrderType, NullsOrder)> {
+    type Error = CustomError;
+
+    fn try_from(o: InterimOrderBy<'a, '_>) -> Result<Self, Self::Error> {
+        let InterimOrderBy(order_by, projection) = o;
+        let order_by_list = match &order_by.kind {
+            OrderByKind::Expressions(order_by_list) if order_by.interpolate.is_none() => {
+                order_by_list
+            }
+            _ => return Err(CustomError::SqlOrderError(order_by.to_string())),
+        };
+
+        let mut keys = Vec::new();
+        for order_by in order_by_list {
+            let error = || CustomError::SqlOrderError(order_by.to_string());
+            if order_by.with_fill.is_some() {
+                return Err(error());
+            }
+            let expr = match &order_by.expr {
+                // ordinal of projection starts from 1
+                SqlExpr::Value(ValueWithSpan {
+                    value: SqlValue::Number(v, _),
+                    ..
+                }) => {
+                    let index = v
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| index.checked_sub(1))
+                        .ok_or_else(error)?;
+                    match projection {
+                        [Expr::Wildcard] => nth(index as i64),
+                        _ => match projection.get(index) {
+                            Some(Expr::Alias(inner_expr, _)) => inner_expr.as_ref().clone(),
+                            Some(Expr::Wildcard) | None => return Err(error()),
+                            Some(expr) => expr.clone(),
+                        },
+                    }
+                }
+                // alias of projection, example: select price * qty as total ... order by total
+                SqlExpr::Identifier(id) => projection
+                    .iter()
+                    .find_map(|expr| match expr {
+                        Expr::Alias(inner_expr, name) if name == id.value.as_str() => {
+                            Some(inner_expr.as_ref().clone())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_else(|| col(id.value.as_str())),
+                v => {
+                    let mut subqueries = Vec::new();
+                    let expr = InterimExpr(v, &mut subqueries).try_into()?;
+                    if !subqueries.is_empty() {
+                        return Err(CustomError::SqlSubqueryError(format!("{} in order by", v)));
+                    }
+                    expr
+                }
+            };
+            let order_type = match order_by.options.asc {
+                Some(false) => OrderType::Desc,
+                _ => OrderType::Asc,
+            };
+            let nulls_order = match (order_by.options.nulls_first, &order_type) {
+                (Some(true), _) | (None, OrderType::Desc) => NullsOrder::First,
+                (Some(false), _) | (None, OrderType::Asc) => NullsOrder::Last,
+            };
+            keys.push((expr, order_type, nulls_order));
+        }
+
+        Ok(keys)
+    }
+}
+
+/// Convert SqlParser offset expr to i64
+impl<'a> From<InterimOffset<'a>> for i64 {
+    fn from(offset: InterimOffset<'a>) -> Self {
+        match offset.0 {
+            SqlOffset {
+                value:
+                    SqlExpr::Value(ValueWithSpan {
+                        value: SqlValue::Number(v, _b),
+                        ..
+                    }),
+                ..
+            } => v.parse().unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+/// Convert SqlParser group by to key expressions, the key computed from an expression is
+/// aliased, by the projection alias when it is selected, example: group by 1, month
+impl<'a> TryFrom<InterimGroupBy<'a, '_>> for Vec<Expr> {
+    type Error = CustomError;
+
+    fn try_from(g: InterimGroupBy<'a, '_>) -> std::result::Result<Self, Self::Error> {
+        let InterimGroupBy(group_by, projection, subqueries) = g;
+        let expr_list = match group_by {
+            GroupByExpr::Expressions(expr_list, modifiers) if modifiers.is_empty() => expr_list,
+            v => return Err(CustomError::SqlGroupByError(v.to_string())),
+        };
+
+        let mut keys = Vec::new();
+        for (i, group_by) in expr_list.iter().enumerate() {
+            let error = || CustomError::SqlGroupByError(group_by.to_string());
+            let key = match group_by {
+                // ordinal of projection starts from 1
+                SqlExpr::Value(ValueWithSpan {
+                    value: SqlValue::Number(v, _),
+                    ..
+                }) => {
+                    let index = v.parse::<usize>().map_err(|_| error())?;
+                    index
+                        .checked_sub(1)
+                        .and_then(|index| projection.get(index))
+                        .cloned()
+                        .ok_or_else(error)?
+                }
+                // alias of projection, example: select d + 1 as next ... group by next
+                SqlExpr::Identifier(id) => projection
+                    .iter()
+                    .find(|expr| matches!(expr, Expr::Alias(_, name) if name == id.value.as_str()))
+                    .cloned()
+                    .unwrap_or_else(|| col(id.value.as_str())),
+                SqlExpr::CompoundIdentifier(idents) => col(qualified_name(idents)),
+                v => {
+                    let expr: Expr = InterimExpr(v, subqueries).try_into()?;
+                    projection
+                        .iter()
+                        .find(|p| matches!(p, Expr::Alias(inner_expr, _) if *inner_expr.as_ref() == expr))
+                        .cloned()
+                        .unwrap_or_else(|| expr.alias(format!("__group_{}", i)))
+                }
+            };
+            if matches!(key, Expr::Wildcard) || has_aggregation(&key) {
+                return Err(error());
+            }
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+}
+
+/// Convert SqlParser limit expr to usize
+impl<'a> From<InterimLimit<'a>> for usize {
+    fn from(l: InterimLimit<'a>) -> Self {
+        match l.0 {
+            SqlExpr::Value(ValueWithSpan { value, .. }) => {
+                if let SqlValue::Number(v, _b) = value {
+                    v.parse().unwrap_or(usize::MAX)
+                } else {
+                    100
+                }
+            }
+            _ => usize::MAX,
+        }
+    }
+}
+
+/// Convert SqlParser Value to LiteralValue of polars
+impl TryFrom<InterimValue> for LiteralValue {
+    type Error = CustomError;
+
+    fn try_from(value: InterimValue) -> Result<Self, Self::Error> {
+        match value.0 {
+            // integer stays integer, so that big ids keep their precision
+            SqlValue::Number(v, _) if !v.contains(['.', 'e', 'E']) => v
+                .parse()
+                .map(|v| LiteralValue::Dyn(DynLiteralValue::Int(v)))
+                .map_err(|_| CustomError::SqlValueError(v)),
+            SqlValue::Number(v, _) => v
+                .parse()
+                .map(|v| LiteralValue::Dyn(DynLiteralValue::Float(v)))
+                .map_err(|_| CustomError::SqlValueError(v)),
+            SqlValue::Boolean(v) => Ok(Scalar::from(v).into()),
+            SqlValue::Null => Ok(LiteralValue::untyped_null()),
+            SqlValue::SingleQuotedString(v)
+            | SqlValue::EscapedStringLiteral(v)
+            | SqlValue::NationalStringLiteral(v) => {
+                Ok(LiteralValue::Dyn(DynLiteralValue::Str(v.into())))
+            }
+            SqlValue::DollarQuotedString(v) => {
+                Ok(LiteralValue::Dyn(DynLiteralValue::Str(v.value.into())))
+            }
+            // hex string is binary, example: X'1F'
+            SqlValue::HexStringLiteral(v) => {
+                let bytes = (0..v.len())
+                    .step_by(2)
+                    .map(|i| v.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| CustomError::SqlValueError(format!("X'{}'", v)))?;
+                Ok(Scalar::new(DataType::Binary, AnyValue::BinaryOwned(bytes)).into())
+            }
+            v => Err(CustomError::SqlValueError(format!("{}", v))),
+        }
+    }
+}
+
+/// Convert SqlParser typed string to LiteralValue of polars, example: DATE '2024-01-01'
+impl TryFrom<InterimTypedValue<'_>> for LiteralValue {
+    type Error = CustomError;
+
+    fn try_from(value: InterimTypedValue<'_>) -> Result<Self, Self::Error> {
+        let InterimTypedValue(data_type, value) = value;
+        let error = || CustomError::SqlValueError(format!("{} {}", data_type, value));
+        let SqlValue::SingleQuotedString(v) = value else {
+            return Err(error());
+        };
+        match data_type {
+            SqlDataType::Date => {
+                let date = NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| error())?;
+                let days = date.signed_duration_since(NaiveDate::default()).num_days();
+                Ok(Scalar::new_date(days as i32).into())
+            }
+            SqlDataType::Timestamp(None, TimezoneInfo::None) | SqlDataType::Datetime(None) => {
+                let datetime = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                    .into_iter()
+                    .find_map(|fmt| NaiveDateTime::parse_from_str(v, fmt).ok())
+                    .or_else(|| {
+                        NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                            .ok()
+                            .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    })
+                    .ok_or_else(error)?;
+                Ok(Scalar::new_datetime(
+                    datetime.and_utc().timestamp_micros(),
+                    TimeUnit::Microseconds,
+                    None,
+                )
+                .into())
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Convert SqlParser data type to polars data type by its name, example: VARCHAR(10)
+impl TryFrom<InterimDataType<'_>> for DataType {
+    type Error = CustomError;
+
+    fn try_from(data_type: InterimDataType<'_>) -> Result<Self, Self::Error> {
+        let name = data_type.0.to_string().to_uppercase();
+        let name = name.split('(').next().unwrap_or_default().trim();
+        SQL_TYPES
+            .iter()
+            .find(|(sql_type, _)| *sql_type == name)
+            .map(|(_, dtype)| dtype.clone())
+            .ok_or_else(|| CustomError::SqlTypeError(data_type.0.to_string()))
+    }
+}
+
+/// Convert SqlParser interval to duration LiteralValue of polars, example: INTERVAL '7 days',
+/// months and years are not supported since their length is not fixed
+impl TryFrom<InterimInterval<'_>> for LiteralValue {
+    type Error = CustomError;
+
+    fn try_from(interval: InterimInterval<'_>) -> Result<Self, Self::Error> {
+        let InterimInterval(interval) = interval;
+        let duration: Duration = InterimInterval(interval).try_into()?;
+        if duration.months() != 0 {
+            return Err(CustomError::SqlValueError(format!("{}", interval)));
+        }
+        Ok(Scalar::new_duration(duration.duration_ns(), TimeUnit::Nanoseconds).into())
+    }
+}
+
+/// Convert SqlParser interval to polars duration, example: INTERVAL 1 MONTH
+impl TryFrom<InterimInterval<'_>> for Duration {
+    type Error = CustomError;
+
+    fn try_from(interval: InterimInterval<'_>) -> Result<Self, Self::Error> {
+        let InterimInterval(interval) = interval;
+        let error = || CustomError::SqlValueError(format!("{}", interval));
+        let SqlExpr::Value(ValueWithSpan {
+            value: SqlValue::SingleQuotedString(v) | SqlValue::Number(v, _),
+            ..
+        }) = interval.value.as_ref()
+        else {
+            return Err(error());
+        };
+        if interval.last_field.is_some()
+            || interval.leading_precision.is_some()
+            || interval.fractional_seconds_precision.is_some()
+        {
+            return Err(error());
+        }
+        let v = match &interval.leading_field {
+            Some(field) => format!("{} {}", v, field),
+            None => v.to_owned(),
+        };
+        Duration::try_parse_interval(&v).map_err(|_| error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::parse_sql;
+
     #[test]
     fn parse_query_sql_work() {
         let url = "http://abc.xyz/abc?a=1&b=2";
@@ -544,83 +2927,875 @@ mod tests {
             LIMIT 5 OFFSET 10",
             url
         );
-        let statement = &Parser::parse_sql(&TyrDialect::default(), sql.as_ref()).unwrap()[0];
+        let statement = &parse_sql(sql.as_ref()).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        // verify data source
+        assert_eq!(sql.source, Source::Table(url));
+        let fisrt_condition = Expr::BinaryExpr {
+            left: Arc::new(Expr::Column("a".into())),
+            op: Operator::Eq,
+            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Int(100)))),
+        };
+        let second_condition = Expr::BinaryExpr {
+            left: Arc::new(Expr::Column("b".into())),
+            op: Operator::Eq,
+            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Int(200)))),
+        };
+        let third_condition = Expr::BinaryExpr {
+            left: Arc::new(Expr::Column("c".into())),
+            op: Operator::Eq,
+            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Int(300)))),
+        };
+        let inner_conditon = Expr::BinaryExpr {
+            left: Arc::new(fisrt_condition),
+            op: Operator::And,
+            right: Arc::new(second_condition),
+        };
+        let condition = Expr::BinaryExpr {
+            left: Arc::new(inner_conditon),
+            op: Operator::And,
+            right: Arc::new(third_condition),
+        };
+        // verify select condition
+        assert_eq!(sql.condition, Some(condition));
+        assert_eq!(sql.limit, Some(5));
+        assert_eq!(sql.offset, Some(10));
+        // verify order by
+        assert_eq!(
+            sql.order_by,
+            vec![
+                (col("c"), OrderType::Asc, NullsOrder::Last),
+                (col("e"), OrderType::Desc, NullsOrder::First),
+                (col("b"), OrderType::Asc, NullsOrder::Last)
+            ]
+        );
+        // verify select item
+        assert_eq!(sql.selection, vec![col("a"), col("b"), col("c")]);
+    }
+
+    #[test]
+    fn parse_group_by_query_sql_work() {
+        let url = "http://abc.xyz/abc?a=1&b=2";
+        let sql = format!(
+            "SELECT
+                a,
+                b,
+                count(*) as test_count
+            FROM {}
+            group by a, b",
+            url
+        );
+        let statement = &parse_sql(sql.as_ref()).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.group_by, vec![col("a"), col("b")]);
+        assert_eq!(sql.selection, vec![col("a"), col("b"), col("test_count")]);
+        assert_eq!(sql.aggregation, vec![len().alias("test_count")])
+    }
+
+    #[test]
+    fn parse_show_columns_sql_work() {
+        let url = "https://raw.githubusercontent.com/ai-aide/query-server/refs/heads/master/resource/owid-covid-latest.csv";
+        let sql = format!("SHOW COLUMNS FROM {}", url);
+        let statement = &parse_sql(sql.as_ref()).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, Source::Table(url));
+    }
+
+    #[test]
+    fn parse_join_sql_work() {
+        let sql = "SELECT a, b FROM http://abc.xyz/a.csv
+            JOIN http://abc.xyz/b.json USING (a)
+            LEFT JOIN http://abc.xyz/c.csv ON b = c";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        let inner = Source::Join {
+            left: Box::new(Source::Table("http://abc.xyz/a.csv")),
+            right: Box::new(Source::Table("http://abc.xyz/b.json")),
+            how: JoinType::Inner,
+            on: JoinOn::Using(vec!["a"]),
+        };
+        assert_eq!(
+            sql.source,
+            Source::Join {
+                left: Box::new(inner),
+                right: Box::new(Source::Table("http://abc.xyz/c.csv")),
+                how: JoinType::Left,
+                on: JoinOn::Expr(col("b").eq(col("c"))),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_table_alias_sql_work() {
+        let sql = r#"SELECT x.a, "y"."b" FROM http://abc.xyz/a.csv AS x
+            JOIN http://abc.xyz/b.json y ON x.a = y.a"#;
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.source,
+            Source::Join {
+                left: Box::new(Source::Alias(
+                    Box::new(Source::Table("http://abc.xyz/a.csv")),
+                    "x"
+                )),
+                right: Box::new(Source::Alias(
+                    Box::new(Source::Table("http://abc.xyz/b.json")),
+                    "y"
+                )),
+                how: JoinType::Inner,
+                on: JoinOn::Expr(col("x.a").eq(col("y.a"))),
+            }
+        );
+        assert_eq!(sql.selection, vec![col("x.a"), col("y.b")]);
+
+        let sql = "SELECT a FROM http://abc.xyz/a.csv AS x (a, b)";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let res: Result<Sql, CustomError> = statement.try_into();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn parse_condition_sql_work() {
+        let sql = "SELECT a FROM http://abc.xyz/a.csv?x=1&y=2 WHERE name='x' AND a!=3 AND b/2>1";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, Source::Table("http://abc.xyz/a.csv?x=1&y=2"));
+        assert_eq!(
+            sql.condition,
+            Some(
+                col("name")
+                    .eq(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Str(
+                        "x".into()
+                    ))))
+                    .and(col("a").neq(lit(3)))
+                    .and((col("b") / lit(2)).gt(lit(1)))
+            )
+        );
+    }
+
+    #[test]
+    fn parse_quoted_identifier_sql_work() {
+        let sql = r#"SELECT "Sepal Length", `a::b`, "c" AS "Total (per 1M)"
+            FROM http://abc.xyz/a.csv WHERE "x=1" > 2 ORDER BY `Sepal Length`"#;
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("Sepal Length"),
+                col("a::b"),
+                col("c").alias("Total (per 1M)")
+            ]
+        );
+        assert_eq!(sql.condition, Some(col("x=1").gt(lit(2))));
+        assert_eq!(
+            sql.order_by,
+            vec![(col("Sepal Length"), OrderType::Asc, NullsOrder::Last)]
+        );
+    }
+
+    #[test]
+    fn resolve_case_work() {
+        let names = ["sepalLength", "species", "Length", "LENGTH"].map(PlSmallStr::from);
+        assert_eq!(
+            resolve_case(col("SEPALLENGTH").gt(col("Length")), &names).unwrap(),
+            col("sepalLength").gt(col("Length"))
+        );
+        assert_eq!(
+            resolve_case(col("unknown"), &names).unwrap(),
+            col("unknown")
+        );
+        assert!(matches!(
+            resolve_case(col("length"), &names),
+            Err(CustomError::SqlAmbiguousColumnError(_))
+        ));
+    }
+
+    #[test]
+    fn parse_table_function_sql_work() {
+        let sql =
+            "SELECT a FROM read_csv('http://abc.xyz/a.txt', delimiter => ';', header => false) AS t
+            JOIN read_parquet(http://abc.xyz/b) USING (a)";
+        let statement = &parse_sql(sql).unwrap()[0];
         let sql: Sql = statement.try_into().unwrap();
-        // verify data source
-        assert_eq!(sql.source, url);
-        let fisrt_condition = Expr::BinaryExpr {
-            left: Arc::new(Expr::Column("a".into())),
-            op: Operator::Eq,
-            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Float(
-                100 as f64,
-            )))),
-        };
-        let second_condition = Expr::BinaryExpr {
-            left: Arc::new(Expr::Column("b".into())),
-            op: Operator::Eq,
-            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Float(
-                200 as f64,
-            )))),
-        };
-        let third_condition = Expr::BinaryExpr {
-            left: Arc::new(Expr::Column("c".into())),
-            op: Operator::Eq,
-            right: Arc::new(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Float(
-                300 as f64,
-            )))),
+        assert_eq!(
+            sql.source,
+            Source::Join {
+                left: Box::new(Source::Alias(
+                    Box::new(Source::Read {
+                        path: "http://abc.xyz/a.txt",
+                        format_type: FormatType::Csv,
+                        options: LoadOptions {
+                            delimiter: Some(b';'),
+                            header: Some(false),
+                            ..Default::default()
+                        },
+                    }),
+                    "t"
+                )),
+                right: Box::new(Source::Read {
+                    path: "http://abc.xyz/b",
+                    format_type: FormatType::Parquet,
+                    options: LoadOptions::default(),
+                }),
+                how: JoinType::Inner,
+                on: JoinOn::Using(vec!["a"]),
+            }
+        );
+
+        for source in [
+            "read_xml('http://abc.xyz/a.xml')",
+            "read_csv(delimiter => ';')",
+            "read_csv('http://abc.xyz/a.csv', delimiter => ';;')",
+            "read_csv('http://abc.xyz/a.csv', header => 'no')",
+            "read_json('http://abc.xyz/a.json', delimiter => ';')",
+            "read_parquet('http://abc.xyz/a.parquet', infer_schema_length => 10)",
+        ] {
+            let sql = format!("SELECT a FROM {}", source);
+            let statement = &parse_sql(&sql).unwrap()[0];
+            let res: Result<Sql, CustomError> = statement.try_into();
+            assert!(
+                matches!(res, Err(CustomError::SqlTableError(_))),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn parse_subquery_sql_work() {
+        let sql = "SELECT a FROM (SELECT a, b FROM http://abc.xyz/a.csv) AS t
+            WHERE a IN (SELECT c FROM http://abc.xyz/b.csv)
+            AND b > (SELECT max(d) FROM http://abc.xyz/c.csv)";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        let Source::Alias(source, "t") = &sql.source else {
+            panic!("unexpected source {:?}", sql.source);
         };
-        let inner_conditon = Expr::BinaryExpr {
-            left: Arc::new(fisrt_condition),
-            op: Operator::And,
-            right: Arc::new(second_condition),
+        let Source::Query(derived) = source.as_ref() else {
+            panic!("unexpected source {:?}", source);
         };
-        let condition = Expr::BinaryExpr {
-            left: Arc::new(inner_conditon),
-            op: Operator::And,
-            right: Arc::new(third_condition),
+        assert_eq!(derived.source, Source::Table("http://abc.xyz/a.csv"));
+        assert_eq!(derived.selection, vec![col("a"), col("b")]);
+        // verify subqueries are replaced by placeholder columns
+        assert_eq!(
+            sql.condition,
+            Some(in_list(col("a"), col("__subquery_0")).and(col("b").gt(col("__subquery_1"))))
+        );
+        let kinds = sql
+            .subqueries
+            .iter()
+            .map(|subquery| subquery.kind)
+            .collect::<Vec<SubqueryKind>>();
+        assert_eq!(kinds, vec![SubqueryKind::List, SubqueryKind::Scalar]);
+        assert_eq!(
+            sql.subqueries[1].sql.source,
+            Source::Table("http://abc.xyz/c.csv")
+        );
+    }
+
+    #[test]
+    fn parse_cte_sql_work() {
+        let sql = "WITH a AS (SELECT x FROM http://abc.xyz/a.csv),
+                b (y) AS (SELECT x FROM a)
+            SELECT y FROM b";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, Source::Table("b"));
+        assert_eq!(sql.ctes.len(), 2);
+        assert_eq!(sql.ctes[0].name, "a");
+        assert_eq!(
+            sql.ctes[0].sql.source,
+            Source::Table("http://abc.xyz/a.csv")
+        );
+        assert_eq!(sql.ctes[1].name, "b");
+        assert_eq!(sql.ctes[1].columns, vec!["y"]);
+        assert_eq!(sql.ctes[1].sql.source, Source::Table("a"));
+    }
+
+    #[test]
+    fn parse_set_operation_sql_work() {
+        let sql = "SELECT a FROM http://abc.xyz/a.csv
+            UNION ALL SELECT b FROM http://abc.xyz/b.csv
+            EXCEPT SELECT c FROM http://abc.xyz/c.csv
+            LIMIT 3";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.selection, vec![Expr::Wildcard]);
+        assert_eq!(sql.limit, Some(3));
+        let Source::Set {
+            left,
+            right,
+            op: SetOperation::Except,
+            all: false,
+        } = &sql.source
+        else {
+            panic!("unexpected source {:?}", sql.source);
         };
-        // verify select condition
-        assert_eq!(sql.condition, Some(condition));
-        assert_eq!(sql.limit, Some(5));
-        assert_eq!(sql.offset, Some(10));
-        // verify order by
+        assert_eq!(right.source, Source::Table("http://abc.xyz/c.csv"));
+        assert!(matches!(
+            left.source,
+            Source::Set {
+                op: SetOperation::Union,
+                all: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_having_sql_work() {
+        let sql = "SELECT a, sum(b) as total FROM http://abc.xyz/a.csv
+            group by a
+            having count(*) > 10 and total > avg(c)";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.aggregation,
+            vec![
+                col("b").sum().alias("total"),
+                len().alias("__agg_1"),
+                col("c").mean().alias("__agg_2"),
+            ]
+        );
+        assert_eq!(
+            sql.having,
+            Some(
+                col("__agg_1")
+                    .gt(lit(10))
+                    .and(col("total").gt(col("__agg_2")))
+            )
+        );
+        assert_eq!(sql.selection, vec![col("a"), col("total")]);
+    }
+
+    #[test]
+    fn parse_group_by_order_sql_work() {
+        let sql = "SELECT a, sum(b) as total FROM http://abc.xyz/a.csv
+            group by a
+            order by count(*) DESC, total ASC
+            limit 10";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.aggregation,
+            vec![col("b").sum().alias("total"), len().alias("__agg_1")]
+        );
         assert_eq!(
             sql.order_by,
             vec![
-                ("c".into(), OrderType::Desc),
-                ("e".into(), OrderType::Desc),
-                ("b".into(), OrderType::Asc)
+                (col("__agg_1"), OrderType::Desc, NullsOrder::First),
+                (col("total"), OrderType::Asc, NullsOrder::Last)
             ]
         );
-        // verify select item
-        assert_eq!(sql.selection, vec![col("a"), col("b"), col("c")]);
+        assert_eq!(sql.limit, Some(10));
     }
 
     #[test]
-    fn parse_group_by_query_sql_work() {
-        let url = "http://abc.xyz/abc?a=1&b=2";
-        let sql = format!(
-            "SELECT
-                a,
-                b,
-                count(*) as test_count
-            FROM {}
-            group by a, b",
-            url
+    fn parse_order_by_expression_sql_work() {
+        let sql = "SELECT a, b * 2 as c FROM http://abc.xyz/a.csv
+            order by 2 desc, c, abs(a) nulls first, d desc nulls last";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.order_by,
+            vec![
+                (col("b") * lit(2), OrderType::Desc, NullsOrder::First),
+                (col("b") * lit(2), OrderType::Asc, NullsOrder::Last),
+                (col("a").abs(), OrderType::Asc, NullsOrder::First),
+                (col("d"), OrderType::Desc, NullsOrder::Last),
+            ]
         );
-        let statement = &Parser::parse_sql(&TyrDialect::default(), sql.as_ref()).unwrap()[0];
+
+        let sql = "SELECT * FROM http://abc.xyz/a.csv order by 3";
+        let statement = &parse_sql(sql).unwrap()[0];
         let sql: Sql = statement.try_into().unwrap();
-        assert_eq!(sql.group_by, vec!["a", "b"]);
-        assert_eq!(sql.selection, vec![col("a"), col("b"), col("test_count")]);
-        assert_eq!(sql.aggregation, vec![len().alias("test_count")])
+        assert_eq!(
+            sql.order_by,
+            vec![(nth(2), OrderType::Asc, NullsOrder::Last)]
+        );
+
+        for order_by in ["0", "3", "a in (select a from t)"] {
+            let sql = format!(
+                "SELECT a, b FROM http://abc.xyz/a.csv order by {}",
+                order_by
+            );
+            let statement = &parse_sql(&sql).unwrap()[0];
+            let res: Result<Sql, CustomError> = statement.try_into();
+            assert!(res.is_err(), "{}", order_by);
+        }
     }
 
     #[test]
-    fn parse_show_columns_sql_work() {
-        let url = "https://raw.githubusercontent.com/ai-aide/query-server/refs/heads/master/resource/owid-covid-latest.csv";
-        let sql = format!("SHOW COLUMNS FROM {}", url);
-        let statement = &Parser::parse_sql(&TyrDialect::default(), sql.as_ref()).unwrap()[0];
+    fn parse_distinct_sql_work() {
+        let sql = "SELECT DISTINCT ON (a) a, b FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.distinct, Some(Distinct::On(vec!["a"])));
+
+        let sql = "SELECT a, count(distinct b) as b_count FROM http://abc.xyz/a.csv group by a";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.distinct, None);
+        assert_eq!(
+            sql.aggregation,
+            vec![col("b").drop_nulls().n_unique().alias("b_count")]
+        );
+    }
+
+    #[test]
+    fn parse_select_expression_sql_work() {
+        let sql = "SELECT a, price * qty AS total, b + 1, (c > 2) and d = 'x' as flag
+            FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a"),
+                (col("price") * col("qty")).alias("total"),
+                (col("b") + lit(1)).alias("b + 1"),
+                col("c")
+                    .gt(lit(2))
+                    .and(
+                        col("d").eq(Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Str(
+                            "x".into()
+                        ))))
+                    )
+                    .alias("flag"),
+            ]
+        );
+
+        let sql = "SELECT a, max(b) - min(b) AS spread FROM http://abc.xyz/a.csv group by a";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.aggregation,
+            vec![
+                col("b").max().alias("__agg_0"),
+                col("b").min().alias("__agg_1"),
+            ]
+        );
+        assert_eq!(
+            sql.selection,
+            vec![col("a"), (col("__agg_0") - col("__agg_1")).alias("spread")]
+        );
+    }
+
+    #[test]
+    fn parse_aggregation_expression_sql_work() {
+        let sql = "SELECT a, min(b), max(b), sum(price * qty) as total, count(*)
+            FROM http://abc.xyz/a.csv group by a";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.aggregation,
+            vec![
+                col("b").min().alias("min(b)"),
+                col("b").max().alias("max(b)"),
+                (col("price") * col("qty")).sum().alias("total"),
+                len().alias("count(*)"),
+            ]
+        );
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a"),
+                col("min(b)"),
+                col("max(b)"),
+                col("total"),
+                col("count(*)")
+            ]
+        );
+
+        let sql = "SELECT a, sum(*) FROM http://abc.xyz/a.csv group by a";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let res: Result<Sql, CustomError> = statement.try_into();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn parse_global_aggregation_sql_work() {
+        let sql = "SELECT count(*), avg(b) + 1 as b_avg FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert!(sql.group_by.is_empty());
+        assert_eq!(
+            sql.aggregation,
+            vec![len().alias("count(*)"), col("b").mean().alias("__agg_1")]
+        );
+        assert_eq!(
+            sql.selection,
+            vec![col("count(*)"), (col("__agg_1") + lit(1)).alias("b_avg")]
+        );
+
+        let sql = "SELECT a, count(*) FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let res: Result<Sql, CustomError> = statement.try_into();
+        assert!(matches!(res, Err(CustomError::SqlAggregationError(_))));
+    }
+
+    #[test]
+    fn parse_extended_aggregation_sql_work() {
+        let sql = "SELECT a, median(b), stddev(b), var_pop(b),
+                percentile_cont(0.9) WITHIN GROUP (ORDER BY b) as p90,
+                string_agg(c, ',') as names
+            FROM http://abc.xyz/a.csv group by a";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.aggregation,
+            vec![
+                col("b").median().alias("median(b)"),
+                col("b").std(1).alias("stddev(b)"),
+                col("b").var(0).alias("var_pop(b)"),
+                col("b")
+                    .quantile(lit(0.9), QuantileMethod::Linear)
+                    .alias("p90"),
+                col("c")
+                    .cast(DataType::String)
+                    .str()
+                    .join(",", true)
+                    .alias("names"),
+            ]
+        );
+
+        for sql in [
+            "SELECT a, percentile_cont(0.9) FROM http://abc.xyz/a.csv group by a",
+            "SELECT a, string_agg(c) FROM http://abc.xyz/a.csv group by a",
+            "SELECT a, median(*) FROM http://abc.xyz/a.csv group by a",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            let res: Result<Sql, CustomError> = statement.try_into();
+            assert!(res.is_err());
+        }
+    }
+
+    #[test]
+    fn parse_window_sql_work() {
+        let sql = "SELECT a, row_number() over (partition by a order by b desc) as rn,
+                sum(c) over (partition by a) as total
+            FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.window,
+            vec![
+                int_range(lit(1), len() + lit(1), 1, IDX_DTYPE)
+                    .over_with_options(
+                        Some(vec![col("a")]),
+                        Some((
+                            vec![col("b")],
+                            SortOptions::default().with_order_descending(true)
+                        )),
+                        WindowMapping::GroupsToRows
+                    )
+                    .unwrap()
+                    .alias("__window_0"),
+                col("c")
+                    .sum()
+                    .over_with_options(Some(vec![col("a")]), None, WindowMapping::GroupsToRows)
+                    .unwrap()
+                    .alias("__window_1"),
+            ]
+        );
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a"),
+                col("__window_0").alias("rn"),
+                col("__window_1").alias("total")
+            ]
+        );
+        assert!(sql.aggregation.is_empty());
+
+        for sql in [
+            "SELECT rank() over (partition by a) FROM http://abc.xyz/a.csv",
+            "SELECT sum(b) over (order by a rows between 1 preceding and 1 following) FROM http://abc.xyz/a.csv",
+            "SELECT row_number() over (order by a asc, b desc) FROM http://abc.xyz/a.csv",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            let res: Result<Sql, CustomError> = statement.try_into();
+            assert!(matches!(res, Err(CustomError::SqlWindowError(_))));
+        }
+    }
+
+    #[test]
+    fn parse_conditional_sql_work() {
+        let sql = "SELECT case when a > 1 then b when a > 0 then c end as x,
+                case a when 1 then b else c end as y,
+                coalesce(a, b, c) as z, nullif(a, b) as w, greatest(a, b) as v
+            FROM http://abc.xyz/a.csv
+            order by case when a > 1 then 0 else 1 end ASC";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                when(col("a").gt(lit(1)))
+                    .then(col("b"))
+                    .otherwise(
+                        when(col("a").gt(lit(0)))
+                            .then(col("c"))
+                            .otherwise(lit(NULL))
+                    )
+                    .alias("x"),
+                when(col("a").eq(lit(1)))
+                    .then(col("b"))
+                    .otherwise(col("c"))
+                    .alias("y"),
+                coalesce(&[col("a"), col("b"), col("c")]).alias("z"),
+                when(col("a").eq(col("b")))
+                    .then(lit(NULL))
+                    .otherwise(col("a"))
+                    .alias("w"),
+                when(col("b").is_null().or(col("a").gt_eq(col("b"))))
+                    .then(col("a"))
+                    .otherwise(col("b"))
+                    .alias("v"),
+            ]
+        );
+        assert_eq!(
+            sql.order_by,
+            vec![(
+                when(col("a").gt(lit(1))).then(lit(0)).otherwise(lit(1)),
+                OrderType::Asc,
+                NullsOrder::Last
+            )]
+        );
+
+        let sql = "SELECT nullif(a) FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let res: Result<Sql, CustomError> = statement.try_into();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn parse_predicate_sql_work() {
+        let sql = "SELECT a FROM http://abc.xyz/a.csv
+            where a in (1, 2) and b not between 1 and 5 and c like 'a\\_%' escape '\\'
+                and d is not null and not (-e > 1)";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.condition,
+            Some(
+                col("a")
+                    .eq(lit(1))
+                    .or(col("a").eq(lit(2)))
+                    .and(col("b").gt_eq(lit(1)).and(col("b").lt_eq(lit(5))).not())
+                    .and(col("c").str().contains(lit("(?s)^a_.*$"), true))
+                    .and(col("d").is_not_null())
+                    .and((-col("e")).gt(lit(1)).not())
+            )
+        );
+
+        let pattern = |sql: &str, similar: bool| {
+            let statement = &parse_sql(&format!("SELECT {}", sql)).unwrap()[0];
+            let Statement::Query(query) = statement else {
+                unreachable!()
+            };
+            let SetExpr::Select(select) = query.body.as_ref() else {
+                unreachable!()
+            };
+            let SelectItem::UnnamedExpr(expr) = &select.projection[0] else {
+                unreachable!()
+            };
+            pattern_regex(expr, None, similar).unwrap()
+        };
+        assert_eq!(pattern("'1.5%'", false), "(?s)^1\\.5.*$");
+        assert_eq!(pattern("'(a|b)_%'", true), "(?s)^(a|b)..*$");
+        assert_eq!(pattern("'(a|b)'", false), "(?s)^\\(a\\|b\\)$");
+    }
+
+    #[test]
+    fn parse_typed_literal_sql_work() {
+        let sql = "SELECT 42 as i, 1.5 as f, true as t, null as n, DATE '1970-01-03' as d,
+                INTERVAL '2' HOUR as h
+            FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                lit(42).alias("i"),
+                lit(1.5).alias("f"),
+                lit(true).alias("t"),
+                Expr::Literal(LiteralValue::untyped_null()).alias("n"),
+                Expr::Literal(Scalar::new_date(2).into()).alias("d"),
+                Expr::Literal(
+                    Scalar::new_duration(7_200_000_000_000, TimeUnit::Nanoseconds).into()
+                )
+                .alias("h"),
+            ]
+        );
+
+        for sql in [
+            "SELECT DATE '2024-02-30' FROM http://abc.xyz/a.csv",
+            "SELECT TIMESTAMP '2024-01-01 25:00:00' FROM http://abc.xyz/a.csv",
+            "SELECT INTERVAL '1 year' FROM http://abc.xyz/a.csv",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            assert!(matches!(
+                Sql::try_from(statement),
+                Err(CustomError::SqlValueError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_cast_sql_work() {
+        let sql = "SELECT CAST(a AS INTEGER) as a, TRY_CAST(b AS VARCHAR(10)) as b, c::date as c,
+                (d + 1)::double precision as d
+            FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a").strict_cast(DataType::Int32).alias("a"),
+                col("b").cast(DataType::String).alias("b"),
+                col("c").strict_cast(DataType::Date).alias("c"),
+                (col("d") + lit(1))
+                    .strict_cast(DataType::Float64)
+                    .alias("d"),
+            ]
+        );
+
+        let sql = "SELECT CAST(a AS GEOMETRY) FROM http://abc.xyz/a.csv";
+        let statement = &parse_sql(sql).unwrap()[0];
+        assert!(matches!(
+            Sql::try_from(statement),
+            Err(CustomError::SqlTypeError(_))
+        ));
+        assert_eq!(sql_type_name(&DataType::Int64), Some("BIGINT"));
+        assert_eq!(
+            sql_type_name(&DataType::Datetime(TimeUnit::Nanoseconds, None)),
+            Some("TIMESTAMP")
+        );
+    }
+
+    #[test]
+    fn parse_string_function_sql_work() {
+        let sql =
+            "SELECT upper(a) as a, substr(b, 2) as b, a || b as c, trim(leading '0' from d) as d
+            FROM http://abc.xyz/a.csv where starts_with(a, 'x')";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        let dyn_str = |v: &str| Expr::Literal(LiteralValue::Dyn(DynLiteralValue::Str(v.into())));
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a").str().to_uppercase().alias("a"),
+                col("b").str().slice(lit(2) - lit(1), lit(NULL)).alias("b"),
+                concat_str([col("a"), col("b")], "", false).alias("c"),
+                col("d").str().strip_chars_start(dyn_str("0")).alias("d"),
+            ]
+        );
+        assert_eq!(
+            sql.condition,
+            Some(col("a").str().starts_with(dyn_str("x")))
+        );
+
+        for sql in [
+            "SELECT lpad(a, b) FROM http://abc.xyz/a.csv",
+            "SELECT lpad(a, 3, b) FROM http://abc.xyz/a.csv",
+            "SELECT split_part(a, '-', 0) FROM http://abc.xyz/a.csv",
+            "SELECT upper(a, b) FROM http://abc.xyz/a.csv",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            assert!(matches!(
+                Sql::try_from(statement),
+                Err(CustomError::SqlExprFuncArgsItem(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_date_function_sql_work() {
+        let sql = "SELECT extract(month from a) as m, date_trunc('day', b) as d,
+                date_add(c, INTERVAL '2 months') as c
+            FROM http://abc.xyz/a.csv where a >= DATE '1970-01-02'";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                to_temporal(col("a")).dt().month().alias("m"),
+                to_temporal(col("b")).dt().truncate(lit("1d")).alias("d"),
+                to_temporal(col("c")).dt().offset_by(lit("2mo")).alias("c"),
+            ]
+        );
+        assert_eq!(
+            sql.condition,
+            Some(to_temporal(col("a")).gt_eq(to_temporal(lit(Scalar::new_date(1)))))
+        );
+
+        for sql in [
+            "SELECT date_trunc('fortnight', a) FROM http://abc.xyz/a.csv",
+            "SELECT date_part('eon', a) FROM http://abc.xyz/a.csv",
+            "SELECT strftime(a, b) FROM http://abc.xyz/a.csv",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            assert!(matches!(
+                Sql::try_from(statement),
+                Err(CustomError::SqlExprFuncArgsItem(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_math_function_sql_work() {
+        let sql = "SELECT abs(a) as a, round(b, 2) as b, floor(c) as c, log(d) as d
+            FROM http://abc.xyz/a.csv where sqrt(e) > 1";
+        let statement = &parse_sql(sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("a").abs().alias("a"),
+                col("b").round(2, RoundMode::HalfAwayFromZero).alias("b"),
+                col("c").floor().alias("c"),
+                col("d").log(10.0).alias("d"),
+            ]
+        );
+        assert_eq!(sql.condition, Some(col("e").sqrt().gt(lit(1))));
+
+        for sql in [
+            "SELECT round(a, b) FROM http://abc.xyz/a.csv",
+            "SELECT round(a, -1) FROM http://abc.xyz/a.csv",
+            "SELECT sqrt(a, b) FROM http://abc.xyz/a.csv",
+        ] {
+            let statement = &parse_sql(sql).unwrap()[0];
+            assert!(matches!(
+                Sql::try_from(statement),
+                Err(CustomError::SqlExprFuncArgsItem(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_group_by_expression_sql_work() {
+        let sql = "SELECT a + 1 as next, upper(b) as name, sum(c) as total
+            FROM http://abc.xyz/a.csv GROUP BY 1, name, d * 2 HAVING d * 2 > 4";
+        let statement = &parse_sql(sql).unwrap()[0];
         let sql: Sql = statement.try_into().unwrap();
-        assert_eq!(sql.source, url);
+        assert_eq!(
+            sql.group_by,
+            vec![
+                (col("a") + lit(1)).alias("next"),
+                col("b").str().to_uppercase().alias("name"),
+                (col("d") * lit(2)).alias("__group_2"),
+            ]
+        );
+        assert_eq!(
+            sql.selection,
+            vec![
+                col("next").alias("next"),
+                col("name").alias("name"),
+                col("total"),
+            ]
+        );
+        assert_eq!(sql.having, Some(col("__group_2").gt(lit(4))));
     }
 }
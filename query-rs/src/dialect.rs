@@ -1,21 +1,62 @@
+use sqlparser::ast::Statement;
 use sqlparser::dialect::Dialect;
+use sqlparser::parser::{Parser, ParserError};
 
 #[derive(Debug, Default)]
 pub struct TyrDialect;
 
 impl Dialect for TyrDialect {
     fn is_identifier_start(&self, ch: char) -> bool {
-        ('a'..='z').contains(&ch) || ('A'..='Z').contains(&ch) || ch == '_'
+        ch.is_ascii_alphabetic() || ch == '_'
     }
 
     fn is_identifier_part(&self, ch: char) -> bool {
-        ('a'..='z').contains(&ch)
-            || ('A'..='Z').contains(&ch)
-            || ('0'..='9').contains(&ch)
-            || [':', '/', '?', '&', '=', '-', '_', '.'].contains(&ch)
+        ch.is_ascii_alphanumeric() || ch == '_'
     }
 }
 
+/// Parse sql of TyrDialect, an unquoted url is a single identifier while the rest of sql
+/// is tokenized as usual, example: select a / 2 from https://abc.xyz/a.csv?x=1
+pub fn parse_sql(sql: &str) -> Result<Vec<Statement>, ParserError> {
+    Parser::parse_sql(&TyrDialect, &quote_url(sql))
+}
+
+/// Quote the urls of sql as identifiers before it is tokenized, so that `--`, `/*` or quotes
+/// of url are kept. The url out of literals and comments starts with a scheme like `http:/`
+/// and ends before whitespace, comma, parenthesis or semicolon
+fn quote_url(sql: &str) -> String {
+    let mut quoted = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(ch) = rest.chars().next() {
+        let skipped = match ch {
+            '\'' | '"' | '`' => rest[1..].find(ch).map_or(rest.len(), |n| n + 2),
+            '-' if rest.starts_with("--") => rest.find('\n').map_or(rest.len(), |n| n + 1),
+            '/' if rest.starts_with("/*") => rest[2..].find("*/").map_or(rest.len(), |n| n + 4),
+            ch if ch.is_ascii_alphabetic() => {
+                let scheme = rest
+                    .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '+' || ch == '.'))
+                    .unwrap_or(rest.len());
+                if rest[scheme..].starts_with(":/") {
+                    let url = rest
+                        .find(|ch: char| ch.is_whitespace() || [',', '(', ')', ';'].contains(&ch))
+                        .unwrap_or(rest.len());
+                    quoted.push('"');
+                    quoted.push_str(&rest[..url].replace('"', "\"\""));
+                    quoted.push('"');
+                    rest = &rest[url..];
+                    continue;
+                }
+                scheme
+            }
+            ch => ch.len_utf8(),
+        };
+        quoted.push_str(&rest[..skipped]);
+        rest = &rest[skipped..];
+    }
+
+    quoted
+}
+
 pub fn example_sql() -> String {
     let url = "https://raw.githubusercontent.com/ai-aide/query-server/refs/heads/master/resource/owid-covid-latest.csv";
     let sql = format!(
@@ -28,10 +69,31 @@ pub fn example_sql() -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sqlparser::parser::Parser;
 
     #[test]
     fn it_works() {
-        assert!(Parser::parse_sql(&TyrDialect::default(), &example_sql()).is_ok())
+        assert!(parse_sql(&example_sql()).is_ok())
+    }
+
+    #[test]
+    fn url_source_work() {
+        let sql = "SELECT a/2, b::int FROM https://abc.xyz/a-b.csv?x=1&y=2 AS t,
+            file:///root/c.json WHERE t.c!='x'";
+        let statement = &parse_sql(sql).unwrap()[0];
+        assert_eq!(
+            statement.to_string(),
+            "SELECT a / 2, b::INT FROM \"https://abc.xyz/a-b.csv?x=1&y=2\" AS t, \
+            \"file:///root/c.json\" WHERE t.c <> 'x'"
+        );
+
+        // comment, quote and literal like parts of url, url of literal is kept
+        let sql = "SELECT * FROM https://abc.xyz/a--b/it's/*.csv WHERE x > 1 -- x
+            AND y = 'http://abc.xyz/a b' /* z */";
+        let statement = &parse_sql(sql).unwrap()[0];
+        assert_eq!(
+            statement.to_string(),
+            "SELECT * FROM \"https://abc.xyz/a--b/it's/*.csv\" WHERE x > 1 \
+            AND y = 'http://abc.xyz/a b'"
+        );
     }
 }
@@ -6,15 +6,15 @@ use tokio::fs;
 #[async_trait]
 pub trait Fetch {
     type Error;
-    async fn fetch(&self) -> Result<String, Self::Error>;
+    async fn fetch(&self) -> Result<Vec<u8>, Self::Error>;
 }
 
-pub async fn retrieve_data(source: impl AsRef<str>) -> FetchResult<String> {
+pub async fn retrieve_data(source: impl AsRef<str>) -> FetchResult<Vec<u8>> {
     let name = source.as_ref();
-    match &name[..4] {
-        "http" => UrlFetcher(name).fetch().await,
-        "file" => FileFetcher(name).fetch().await,
-        v => Err(CustomError::FetchResourceError(v.to_string())),
+    match name.get(..4) {
+        Some("http") => UrlFetcher(name).fetch().await,
+        Some("file") => FileFetcher(name).fetch().await,
+        _ => Err(CustomError::FetchResourceError(name.to_string())),
     }
 }
 
@@ -25,18 +25,18 @@ struct FileFetcher<'a>(pub(crate) &'a str);
 impl<'a> Fetch for UrlFetcher<'a> {
     type Error = CustomError;
 
-    async fn fetch(&self) -> Result<String, Self::Error> {
+    async fn fetch(&self) -> Result<Vec<u8>, Self::Error> {
         let resp = reqwest::get(self.0)
             .await
             .map_err(|e| CustomError::FetchError {
                 url: self.0.to_string(),
                 error: e.to_string(),
             })?;
-        let body = resp.text().await.map_err(|e| CustomError::FetchError {
+        let body = resp.bytes().await.map_err(|e| CustomError::FetchError {
             url: self.0.to_string(),
             error: e.to_string(),
         })?;
-        Ok(body)
+        Ok(body.to_vec())
     }
 }
 
@@ -44,13 +44,15 @@ impl<'a> Fetch for UrlFetcher<'a> {
 impl<'a> Fetch for FileFetcher<'a> {
     type Error = CustomError;
 
-    async fn fetch(&self) -> Result<String, Self::Error> {
-        let body = fs::read_to_string(&self.0[7..])
-            .await
-            .map_err(|e| CustomError::FetchError {
-                url: self.0.to_string(),
-                error: e.to_string(),
-            })?;
+    async fn fetch(&self) -> Result<Vec<u8>, Self::Error> {
+        let path = self
+            .0
+            .strip_prefix("file://")
+            .ok_or_else(|| CustomError::FetchResourceError(self.0.to_string()))?;
+        let body = fs::read(path).await.map_err(|e| CustomError::FetchError {
+            url: self.0.to_string(),
+            error: e.to_string(),
+        })?;
         Ok(body)
     }
 }
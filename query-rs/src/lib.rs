@@ -2,17 +2,18 @@ pub mod convert;
 pub mod dialect;
 pub mod fetcher;
 pub mod loader;
+mod planner;
 
 use crate::loader::FormatType;
 use anyhow::Result;
-use convert::{OrderType, Sql};
+pub use convert::Resolution;
+use convert::Sql;
 pub use dialect::TyrDialect;
-pub use dialect::example_sql;
-use fetcher::retrieve_data;
-use loader::detect_content;
+pub use dialect::{example_sql, parse_sql};
+use planner::Planner;
 use polars::prelude::*;
-use sqlparser::parser::Parser;
 use std::convert::TryInto;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
 use thiserror::Error;
 
@@ -27,16 +28,36 @@ pub enum CustomError {
     SqlOperatorError(String),
     #[error("sql table {0} is not supported")]
     SqlTableError(String),
+    #[error("sql join {0} is not supported")]
+    SqlJoinError(String),
+    #[error("sql subquery {0} is not supported")]
+    SqlSubqueryError(String),
+    #[error("sql cte {0} is not supported")]
+    SqlCteError(String),
+    #[error("sql set operation {0} is not supported")]
+    SqlSetOperationError(String),
+    #[error("sql aggregation {0} is not supported")]
+    SqlAggregationError(String),
+    #[error("sql window {0} is not supported")]
+    SqlWindowError(String),
     #[error("sql select item {0} is not supported")]
     SqlSelectItemError(String),
     #[error("sql expr function item {0} is not supported")]
     SqlExprFuncItem(String),
     #[error("sql expr function args item {0} is not supported")]
     SqlExprFuncArgsItem(String),
+    #[error("sql group by {0} is not supported")]
+    SqlGroupByError(String),
     #[error("sql order by {0} is not supported")]
     SqlOrderError(String),
+    #[error("sql column {0} is not found")]
+    SqlColumnError(String),
+    #[error("sql column {0} is ambiguous")]
+    SqlAmbiguousColumnError(String),
     #[error("sql value {0} is not supported")]
     SqlValueError(String),
+    #[error("sql type {0} is not supported")]
+    SqlTypeError(String),
     #[error("sql statement {0} is not supported")]
     SqlStatementError(String),
     #[error("sql convert {0} is not supported")]
@@ -85,6 +106,16 @@ impl DerefMut for ColumnType {
     }
 }
 
+/// Report column type by its sql type name, polars name is used when there is no sql type
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match convert::sql_type_name(&self.0) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
 impl DataSet {
     /// Convert DataSet To Csv
     pub fn to_csv(&mut self) -> Result<String> {
@@ -107,8 +138,7 @@ pub async fn show_columns<T: AsRef<str>>(
     sql: T,
     format_type: FormatType,
 ) -> QueryResult<Vec<(String, ColumnType)>> {
-    let ast = Parser::parse_sql(&TyrDialect::default(), sql.as_ref())
-        .map_err(|e| CustomError::SqlConvertError(e.to_string()))?;
+    let ast = parse_sql(sql.as_ref()).map_err(|e| CustomError::SqlConvertError(e.to_string()))?;
 
     if ast.len() != 1 {
         return Err(CustomError::SqlConvertError(format!("{:?}", ast)));
@@ -116,102 +146,62 @@ pub async fn show_columns<T: AsRef<str>>(
 
     let Sql { source, .. } = (&ast[0]).try_into()?;
 
-    let ds = detect_content(format_type, retrieve_data(source).await?)
-        .load()
-        .map_err(|e| CustomError::FetchError {
-            url: "".to_string(),
+    let schema = Planner::new(format_type)
+        .source(&source)
+        .await?
+        .collect_schema()
+        .map_err(|e| CustomError::PolarsError {
             error: e.to_string(),
         })?;
 
-    let list = ds
-        .fields()
-        .into_iter()
-        .map(|inner| (inner.name.to_string(), ColumnType(inner.dtype)))
+    let list = schema
+        .iter()
+        .map(|(name, dtype)| (name.to_string(), ColumnType(dtype.clone())))
         .collect::<Vec<(String, ColumnType)>>();
 
     Ok(list)
 }
 
 pub async fn query<T: AsRef<str>>(sql: T, format_type: FormatType) -> QueryResult<DataSet> {
-    let ast = Parser::parse_sql(&TyrDialect::default(), sql.as_ref())
-        .map_err(|e| CustomError::SqlConvertError(e.to_string()))?;
+    query_with_resolution(sql, format_type, Resolution::default()).await
+}
+
+/// Query with the resolution of column names, example: case insensitive column names
+pub async fn query_with_resolution<T: AsRef<str>>(
+    sql: T,
+    format_type: FormatType,
+    resolution: Resolution,
+) -> QueryResult<DataSet> {
+    let ast = parse_sql(sql.as_ref()).map_err(|e| CustomError::SqlConvertError(e.to_string()))?;
 
     if ast.len() != 1 {
         return Err(CustomError::SqlConvertError(format!("{:?}", ast)));
     }
 
-    let Sql {
-        source,
-        condition,
-        selection,
-        aggregation,
-        offset,
-        limit,
-        order_by,
-        group_by,
-    } = (&ast[0]).try_into()?;
-
-    let ds = detect_content(format_type, retrieve_data(source).await?)
-        .load()
-        .map_err(|e| CustomError::FetchError {
-            url: "".to_string(),
-            error: e.to_string(),
-        })?;
-    let mut filtered = match condition {
-        Some(expr) => ds.0.lazy().filter(expr),
-        None => ds.0.lazy(),
-    };
-
-    let dataset = if group_by.len() > 0 {
-        // group by select
-        let filtered = filtered.group_by(
-            group_by
-                .iter()
-                .map(|item| col(*item))
-                .collect::<Vec<Expr>>(),
-        );
-        DataSet(
-            filtered
-                .agg(aggregation)
-                .with_new_streaming(true)
-                .select(selection)
-                .collect()
-                .map_err(|e| CustomError::PolarsError {
-                    error: e.to_string(),
-                })?,
-        )
-    } else {
-        // general select
-        let order_list = order_by
-            .into_iter()
-            .map(|(col, order_type)| (col, order_type == OrderType::Desc))
-            .collect::<Vec<(&str, bool)>>();
-        let (cols, orders): (Vec<&str>, Vec<bool>) = order_list.into_iter().unzip();
-
-        filtered = filtered.sort(
-            cols,
-            SortMultipleOptions::default().with_order_descending_multi(orders),
-        );
-
-        if offset.is_some() || limit.is_some() {
-            filtered = filtered.slice(offset.unwrap_or(0), limit.unwrap_or(20) as IdxSize);
-        }
-
-        DataSet(
-            filtered
-                .select(selection)
-                .with_new_streaming(true)
-                .collect()
-                .map_err(|e| CustomError::PolarsError {
-                    error: e.to_string(),
-                })?,
-        )
-    };
+    let sql: Sql = (&ast[0]).try_into()?;
+
+    let dataset = DataSet(
+        Planner::new(format_type)
+            .with_resolution(resolution)
+            .plan(sql)
+            .await?
+            .with_new_streaming(true)
+            .collect()
+            .map_err(|e| CustomError::PolarsError {
+                error: e.to_string(),
+            })?,
+    );
 
     Ok(dataset)
 }
 
 #[cfg(test)]
+#[allow(
+    unused_imports,
+    clippy::single_component_path_imports,
+    clippy::bool_assert_comparison,
+    clippy::assertions_on_constants
+)]
 mod tests {
     use std::any;
 
@@ -220,6 +210,10 @@ mod tests {
     use tokio;
     use tracing_subscriber::fmt::format;
 
+    fn resource(name: &str) -> String {
+        format!("file://{}/../resource/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
     #[tokio::test]
     async fn csv_show_columns_work() {
         let show_columns_sql = "SHOW COLUMNS FROM https://raw.githubusercontent.com/ai-aide/query-server/refs/heads/master/resource/owid-covid-latest.csv";
@@ -229,6 +223,7 @@ mod tests {
             assert_eq!(column_list.len(), 67);
             assert_eq!(column_list[0].0, "iso_code");
             assert_eq!(column_list[1].1, ColumnType(DataType::String));
+            assert_eq!(column_list[1].1.to_string(), "VARCHAR");
         }
     }
 
@@ -302,6 +297,7 @@ mod tests {
             assert_eq!(column_list.len(), 5);
             assert_eq!(column_list[0].0, "sepalLength");
             assert_eq!(column_list[1].1, ColumnType(DataType::Float64));
+            assert_eq!(column_list[1].1.to_string(), "DOUBLE");
         }
     }
 
@@ -362,4 +358,1182 @@ mod tests {
             assert_eq!(dataset.width(), 5);
         }
     }
+
+    #[tokio::test]
+    async fn invalid_source_query_work() {
+        for source in ["files", "file", "file:/root/a.csv", "ftp://abc.xyz/a.csv"] {
+            let sql = format!("SELECT * FROM {}", source);
+            let res = query(&sql, FormatType::Csv).await;
+            assert!(
+                matches!(res, Err(CustomError::FetchResourceError(_))),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn join_query_work() {
+        let sql = format!(
+            "SELECT species, common_name, sepalLength FROM {} JOIN {} USING (species) WHERE sepalLength > 5.0",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 69);
+            assert_eq!(dataset.width(), 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn left_join_query_work() {
+        let sql = format!(
+            "SELECT i.species, s.common_name FROM {} AS i LEFT JOIN {} AS s ON i.species = s.species",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 150);
+            assert_eq!(dataset.column("common_name").unwrap().null_count(), 50);
+        }
+
+        // unqualified key of both sides
+        let sql = format!(
+            "SELECT species, common_name FROM {} LEFT JOIN {} ON species = species",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(matches!(res, Err(CustomError::SqlAmbiguousColumnError(_))));
+
+        // condition of the side not preserved filters it before the join
+        for sql in [
+            "SELECT i.species, s.common_name FROM {iris} AS i LEFT JOIN {species} AS s ON i.species = s.species AND s.native_region <> 'Arctic'",
+            "SELECT i.species, s.common_name FROM {species} AS s RIGHT JOIN {iris} AS i ON i.species = s.species AND s.native_region <> 'Arctic'",
+        ] {
+            let sql = sql
+                .replace("{iris}", &resource("iris.json"))
+                .replace("{species}", &resource("species.csv"));
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), 150);
+                assert_eq!(dataset.column("common_name").unwrap().null_count(), 100);
+            }
+        }
+
+        // condition of the preserved side
+        let sql = format!(
+            "SELECT i.species, s.common_name FROM {} AS i LEFT JOIN {} AS s ON i.species = s.species AND i.sepalLength > 5",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(matches!(res, Err(CustomError::SqlJoinError(_))));
+    }
+
+    #[tokio::test]
+    async fn cross_join_query_work() {
+        let sql = format!(
+            "SELECT * FROM {}, {}",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 300);
+            assert_eq!(dataset.width(), 8);
+        }
+    }
+
+    #[tokio::test]
+    async fn derived_table_query_work() {
+        let sql = format!(
+            "SELECT species, total FROM (SELECT species, sum(sepalLength) as total FROM {} group by species) AS t WHERE total > 300",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            assert_eq!(dataset.width(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn in_subquery_query_work() {
+        let sql = format!(
+            "SELECT * FROM {} WHERE species NOT IN (SELECT species FROM {})",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 50);
+        }
+
+        // a value out of the list with null is unknown
+        for (sql, height) in [
+            (
+                "SELECT * FROM {iris} WHERE species NOT IN (SELECT common_name FROM {species})",
+                150,
+            ),
+            (
+                "SELECT * FROM {iris} WHERE species NOT IN
+                (SELECT common_name FROM {iris} LEFT JOIN {species} USING (species))",
+                0,
+            ),
+        ] {
+            let sql = sql
+                .replace("{iris}", &resource("iris.json"))
+                .replace("{species}", &resource("species.csv"));
+            let res = query(&sql, FormatType::Json).await;
+            assert!(res.is_ok(), "{:?}", res);
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height, "{}", sql);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn scalar_subquery_query_work() {
+        let sql = format!(
+            "SELECT sepalLength FROM {} WHERE sepalLength > (SELECT avg(sepalLength) FROM {})",
+            resource("iris.json"),
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 70);
+        }
+    }
+
+    #[tokio::test]
+    async fn exists_subquery_query_work() {
+        let sql = format!(
+            "SELECT * FROM {} WHERE EXISTS (SELECT * FROM {} WHERE species = 'virginica')",
+            resource("iris.json"),
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn cte_query_work() {
+        let sql = format!(
+            "WITH lookup AS (SELECT species, common_name FROM {}),
+                big AS (SELECT species, sepalLength FROM {} WHERE sepalLength > 5.0),
+                joined AS (SELECT species, common_name, sepalLength FROM big JOIN lookup USING (species))
+            SELECT * FROM joined",
+            resource("species.csv"),
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 69);
+            assert_eq!(dataset.width(), 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn cte_column_alias_query_work() {
+        let sql = format!(
+            "WITH lookup (name) AS (SELECT species FROM {}) SELECT name FROM lookup",
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 2);
+            assert_eq!(dataset.get_column_names(), vec!["name"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn union_query_work() {
+        let iris = resource("iris.json");
+        let species = resource("species.csv");
+        for (op, height) in [
+            ("UNION ALL", 152),
+            ("UNION", 3),
+            ("INTERSECT", 2),
+            ("EXCEPT", 1),
+        ] {
+            let sql = format!(
+                "SELECT species FROM {} {} SELECT species FROM {}",
+                iris, op, species
+            );
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn union_mismatch_query_work() {
+        let iris = resource("iris.json");
+        let species = resource("species.csv");
+        let sql = format!(
+            "SELECT species, sepalLength FROM {} UNION SELECT species FROM {}",
+            iris, species
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(matches!(res, Err(CustomError::SqlSetOperationError(_))));
+
+        let sql = format!(
+            "SELECT sepalLength FROM {} UNION SELECT species FROM {}",
+            iris, species
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(matches!(res, Err(CustomError::SqlSetOperationError(_))));
+    }
+
+    #[tokio::test]
+    async fn having_query_work() {
+        let iris = resource("iris.json");
+        for (having, height) in [
+            ("count(*) > 10 and sum(sepalLength) > 260", 2),
+            ("total > 300", 1),
+            ("max(petalLength) > 5 and total < avg(sepalLength) * 60", 2),
+        ] {
+            let sql = format!(
+                "SELECT species, sum(sepalLength) as total FROM {} group by species having {}",
+                iris, having
+            );
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height);
+                assert_eq!(dataset.get_column_names(), vec!["species", "total"]);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn group_by_order_limit_query_work() {
+        let iris = resource("iris.json");
+        for (order_limit, first, height) in [
+            ("order by total DESC limit 2", "virginica", 2),
+            (
+                "order by count(*) DESC, species ASC limit 1 offset 1",
+                "versicolor",
+                1,
+            ),
+            ("order by min(sepalWidth) ASC", "versicolor", 3),
+            ("order by species DESC limit 10", "virginica", 3),
+        ] {
+            let sql = format!(
+                "SELECT species, sum(sepalLength) as total FROM {} group by species {}",
+                iris, order_limit
+            );
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height);
+                assert_eq!(dataset.width(), 2);
+                let value = dataset.column("species").unwrap().get(0).unwrap();
+                assert_eq!(value.get_str(), Some(first));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn distinct_query_work() {
+        let iris = resource("iris.json");
+        for (select, height) in [
+            ("SELECT DISTINCT species FROM {}", 3),
+            (
+                "SELECT DISTINCT species FROM {} order by species ASC limit 2",
+                2,
+            ),
+            (
+                "SELECT DISTINCT ON (species) species, sepalLength FROM {}",
+                3,
+            ),
+        ] {
+            let sql = select.replace("{}", &iris);
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn distinct_aggregation_query_work() {
+        let sql = format!(
+            "SELECT species, count(distinct sepalLength) as length_count, count(sepalLength) as total_count FROM {} group by species order by species ASC",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            let length_count = dataset.column("length_count").unwrap().get(0).unwrap();
+            let total_count = dataset.column("total_count").unwrap().get(0).unwrap();
+            assert_eq!(length_count.try_extract::<u32>().unwrap(), 15);
+            assert_eq!(total_count.try_extract::<u32>().unwrap(), 50);
+        }
+    }
+
+    #[tokio::test]
+    async fn select_expression_query_work() {
+        let sql = format!(
+            "SELECT species, sepalLength * sepalWidth AS area, petalLength + 1, sepalLength > 5 and species = 'setosa' as big FROM {} limit 5",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 5);
+            assert_eq!(
+                dataset.get_column_names(),
+                vec!["species", "area", "petalLength + 1", "big"]
+            );
+            let area = dataset.column("area").unwrap().get(0).unwrap();
+            assert!((area.try_extract::<f64>().unwrap() - 17.85).abs() < 1e-9);
+            let big = dataset.column("big").unwrap().get(0).unwrap();
+            assert_eq!(big, AnyValue::Boolean(true));
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregation_expression_query_work() {
+        let sql = format!(
+            "SELECT species, min(sepalLength), max(sepalLength), sum(sepalLength * 2) as double_total FROM {} group by species order by species ASC",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(
+                dataset.get_column_names(),
+                vec![
+                    "species",
+                    "min(sepalLength)",
+                    "max(sepalLength)",
+                    "double_total"
+                ]
+            );
+            let min = dataset.column("min(sepalLength)").unwrap().get(0).unwrap();
+            let max = dataset.column("max(sepalLength)").unwrap().get(0).unwrap();
+            let total = dataset.column("double_total").unwrap().get(0).unwrap();
+            assert_eq!(min.try_extract::<f64>().unwrap(), 4.3);
+            assert_eq!(max.try_extract::<f64>().unwrap(), 5.8);
+            assert!((total.try_extract::<f64>().unwrap() - 500.6).abs() < 1e-9);
+        }
+    }
+
+    #[tokio::test]
+    async fn global_aggregation_query_work() {
+        let sql = format!(
+            "SELECT count(*), avg(sepalLength) as length_avg, max(sepalLength) - min(sepalLength) as length_range FROM {}",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            assert_eq!(
+                dataset.get_column_names(),
+                vec!["count(*)", "length_avg", "length_range"]
+            );
+            let count = dataset.column("count(*)").unwrap().get(0).unwrap();
+            let range = dataset.column("length_range").unwrap().get(0).unwrap();
+            assert_eq!(count.try_extract::<u32>().unwrap(), 150);
+            assert!((range.try_extract::<f64>().unwrap() - 3.6).abs() < 1e-9);
+        }
+
+        let sql = format!("SELECT species, count(*) FROM {}", resource("iris.json"));
+        let res = query(sql, FormatType::Json).await;
+        assert!(matches!(res, Err(CustomError::SqlAggregationError(_))));
+    }
+
+    #[tokio::test]
+    async fn extended_aggregation_query_work() {
+        let sql = format!(
+            "SELECT species, median(sepalLength) as length_median, stddev_pop(sepalLength) as length_std, percentile_cont(0.5) WITHIN GROUP (ORDER BY sepalLength) as length_p50, approx_count_distinct(sepalLength) as length_count, first(sepalLength) as length_first, array_agg(sepalLength) as lengths, string_agg(species, ',') as names FROM {} group by species order by species ASC",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 3);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("length_median").try_extract::<f64>().unwrap(), 5.0);
+            assert_eq!(value("length_p50").try_extract::<f64>().unwrap(), 5.0);
+            assert_eq!(value("length_first").try_extract::<f64>().unwrap(), 5.1);
+            assert!(value("length_std").try_extract::<f64>().unwrap() > 0.0);
+            assert!(value("length_count").try_extract::<u32>().unwrap() > 10);
+            assert!(matches!(value("lengths"), AnyValue::List(s) if s.len() == 50));
+            assert!(
+                matches!(value("names"), AnyValue::String(s) if s.starts_with("setosa,setosa"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn window_query_work() {
+        // rows of null name are peers, last in ascending order by default
+        for (order, count) in [
+            ("", 150),
+            (" nulls first", 50),
+            (" desc", 50),
+            (" desc nulls last", 150),
+        ] {
+            let sql = format!(
+                "SELECT common_name, count(*) over (order by common_name{}) as n
+                FROM {} LEFT JOIN {} USING (species)",
+                order,
+                resource("iris.json"),
+                resource("species.csv")
+            );
+            let res = query(&sql, FormatType::Json).await;
+            assert!(res.is_ok(), "{:?}", res);
+            if let Ok(dataset) = res {
+                let n = dataset.column("n").unwrap();
+                assert_eq!(n.n_unique().unwrap(), 3);
+                let nulls = dataset.column("common_name").unwrap().is_null();
+                let n = n.filter(&nulls).unwrap();
+                assert_eq!(n.min_reduce().unwrap().value(), &AnyValue::UInt32(count));
+                assert_eq!(n.max_reduce().unwrap().value(), &AnyValue::UInt32(count));
+            }
+        }
+
+        let sql = format!(
+            "SELECT species, sepalLength,
+                row_number() over (partition by species order by sepalLength desc) as rn,
+                rank() over (partition by species order by sepalLength desc) as length_rank,
+                dense_rank() over (partition by species order by sepalLength desc) as length_dense_rank,
+                lag(sepalLength) over (partition by species order by sepalLength) as previous,
+                sum(sepalLength) over (partition by species) as total,
+                sum(sepalLength) over (partition by species order by sepalLength desc) as running,
+                sum(sepalLength) over (partition by species order by sepalLength desc rows between unbounded preceding and current row) as running_rows,
+                last_value(sepalLength + sepalWidth) over (partition by species order by sepalLength desc) as last_peer,
+                sum(sepalLength) over (partition by species order by sepalLength desc rows between 2 preceding and current row) as recent,
+                row_number() over (order by sepalLength) as global_rn,
+                ntile(4) over (partition by species order by sepalLength) as quartile
+            FROM {} order by species ASC, sepalLength DESC limit 5",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 5);
+            let values = |name: &str| -> Vec<f64> {
+                let column = dataset
+                    .column(name)
+                    .unwrap()
+                    .cast(&DataType::Float64)
+                    .unwrap();
+                column.f64().unwrap().into_no_null_iter().collect()
+            };
+            let mut rn = values("rn");
+            rn.sort_by(f64::total_cmp);
+            assert_eq!(rn, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+            assert_eq!(values("length_rank"), vec![1.0, 2.0, 2.0, 4.0, 4.0]);
+            assert_eq!(values("length_dense_rank"), vec![1.0, 2.0, 2.0, 3.0, 3.0]);
+            assert_eq!(values("previous")[0], 5.7);
+            assert!(values("total").iter().all(|v| (v - 250.3).abs() < 1e-9));
+            // peers of the same length share the frame ending at the last of them
+            let expected = [
+                (values("running"), [5.8, 17.2, 17.2, 28.2, 28.2]),
+                (values("running_rows"), [5.8, 11.5, 17.2, 22.7, 28.2]),
+                (values("recent"), [5.8, 11.5, 17.2, 16.9, 16.7]),
+            ];
+            for (actual, expected) in expected {
+                for (a, e) in actual.iter().zip(expected) {
+                    assert!((a - e).abs() < 1e-9);
+                }
+            }
+            let last_peer = values("last_peer");
+            assert_eq!(last_peer[1], last_peer[2]);
+            assert_eq!(last_peer[3], last_peer[4]);
+            // 73 rows are shorter than 5.8 and 7 rows tie
+            assert!((74.0..=80.0).contains(&values("global_rn")[0]));
+            // 50 rows are distributed as 13, 13, 12, 12
+            assert_eq!(values("quartile"), vec![4.0; 5]);
+        }
+    }
+
+    #[tokio::test]
+    async fn conditional_query_work() {
+        let sql = format!(
+            "SELECT species,
+                case when sepalLength >= 7 then 'long' when sepalLength >= 5 then 'medium' else 'short' end as size,
+                case species when 'setosa' then 1 else 0 end as is_setosa,
+                coalesce(nullif(species, 'setosa'), 'other') as kind,
+                greatest(sepalLength, petalLength) as longest,
+                least(sepalWidth, petalWidth) as shortest
+            FROM {} where case when species = 'setosa' then sepalLength else 0 end > 5.5",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 3);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("size"), AnyValue::String("medium"));
+            assert_eq!(value("is_setosa").try_extract::<f64>().unwrap(), 1.0);
+            assert_eq!(value("kind"), AnyValue::String("other"));
+            assert_eq!(value("longest").try_extract::<f64>().unwrap(), 5.8);
+            assert_eq!(value("shortest").try_extract::<f64>().unwrap(), 0.2);
+        }
+    }
+
+    #[tokio::test]
+    async fn predicate_query_work() {
+        let iris = resource("iris.json");
+        for (condition, height) in [
+            ("species IN ('setosa', 'virginica')", 100),
+            ("species NOT IN ('setosa')", 100),
+            ("sepalLength BETWEEN 5 AND 6", 67),
+            ("species LIKE '_e%'", 100),
+            ("species ILIKE 'SET%'", 50),
+            ("species NOT LIKE 'set%'", 100),
+            ("species SIMILAR TO '(set|vir)%'", 100),
+            ("species ~ '^vir'", 50),
+            ("species ~* '^VIR'", 50),
+            ("species !~ '^vir'", 100),
+            ("NOT (sepalLength > 5)", 32),
+            ("-sepalLength < -7", 12),
+            ("species IS NULL", 0),
+            ("species IS NOT NULL", 150),
+        ] {
+            let sql = format!("SELECT species FROM {} where {}", iris, condition);
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height, "{}", condition);
+            }
+        }
+
+        // unknown of null is neither in nor not in the list
+        for (condition, height) in [
+            ("common_name IN ('Harlequin blueflag')", 50),
+            ("common_name NOT IN ('Harlequin blueflag')", 50),
+            ("common_name IS NULL", 50),
+        ] {
+            let sql = format!(
+                "SELECT i.species, common_name FROM {} AS i
+                LEFT JOIN {} AS s ON i.species = s.species where {}",
+                iris,
+                resource("species.csv"),
+                condition
+            );
+            let res = query(sql, FormatType::Json).await;
+            assert!(res.is_ok());
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height, "{}", condition);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn typed_literal_query_work() {
+        let sql = format!(
+            "SELECT location,
+                9007199254740993 as big,
+                true as flag,
+                null as nothing,
+                X'4869' as bytes,
+                DATE '2024-08-04' as day,
+                TIMESTAMP '2024-08-04 12:30:00' + INTERVAL '1 day 2 hours' as later
+            FROM {} where total_cases > 100000000 and population > 300000000 and iso_code <> 'OWID_WRL'",
+            resource("owid-covid-latest.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert!(dataset.height() > 0);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("big"), AnyValue::Int64(9007199254740993));
+            assert_eq!(value("flag"), AnyValue::Boolean(true));
+            assert_eq!(value("nothing"), AnyValue::Null);
+            assert_eq!(value("bytes"), AnyValue::Binary(b"Hi"));
+            assert_eq!(value("day").to_string(), "2024-08-04");
+            assert_eq!(value("later").to_string(), "2024-08-05 14:30:00");
+        }
+
+        for sql in [
+            "SELECT DATE '2024-13-01' as day FROM {}",
+            "SELECT INTERVAL '1 month' as period FROM {}",
+            "SELECT X'486' as bytes FROM {}",
+        ] {
+            let sql = sql.replace("{}", &resource("owid-covid-latest.csv"));
+            assert!(query(sql, FormatType::Csv).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn cast_query_work() {
+        let sql = format!(
+            "SELECT location,
+                CAST(total_cases AS BIGINT) as cases,
+                population::varchar as population_text,
+                TRY_CAST(continent AS INT) as continent_number,
+                CAST(last_updated_date AS DATE) as updated,
+                (total_deaths / 2)::int as half_deaths
+            FROM {} where iso_code = 'AFG'",
+            resource("owid-covid-latest.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("cases"), AnyValue::Int64(235214));
+            assert_eq!(value("population_text"), AnyValue::String("41128772.0"));
+            assert_eq!(value("continent_number"), AnyValue::Null);
+            assert_eq!(value("updated").to_string(), "2024-08-04");
+            assert_eq!(value("half_deaths"), AnyValue::Int32(3999));
+        }
+
+        // strict cast fails on the value which can not be converted
+        let sql = format!(
+            "SELECT CAST(continent AS INT) as continent_number FROM {}",
+            resource("owid-covid-latest.csv")
+        );
+        assert!(query(sql, FormatType::Csv).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn string_function_query_work() {
+        let sql = format!(
+            "SELECT upper(species) as upper_name,
+                lower('ABC') as lower_name,
+                length(common_name) as name_length,
+                substr(common_name, 1, 7) as prefix,
+                substring(common_name from 10) as suffix,
+                replace(common_name, '-', ' ') as replaced,
+                concat(species, ':', native_region) as joined,
+                species || '/' || native_region as piped,
+                split_part(common_name, '-', 2) as second_part,
+                trim(both 'x' from 'xxsetosaxx') as trimmed,
+                ltrim('  a ') as left_trimmed,
+                rtrim('  a ') as right_trimmed,
+                lpad(species, 8, '*') as left_padded,
+                rpad(species, 3) as right_padded,
+                lpad('a', 4, 'xy') as left_filled,
+                rpad('a', 3, 'xy') as right_filled,
+                substr(species, 0, 3) as head,
+                substr(species, -2) as whole,
+                regexp_replace(common_name, '[aeiou]', '_', 'g') as masked,
+                regexp_extract(common_name, '(\\w+)-(\\w+)', 2) as extracted
+            FROM {} where starts_with(species, 'se') and ends_with(common_name, 'iris')
+                and contains(common_name, 'point') and regexp_like(native_region, '^Arc')",
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("upper_name"), AnyValue::String("SETOSA"));
+            assert_eq!(value("lower_name"), AnyValue::String("abc"));
+            assert_eq!(value("name_length"), AnyValue::UInt32(20));
+            assert_eq!(value("prefix"), AnyValue::String("Bristle"));
+            assert_eq!(value("suffix"), AnyValue::String("ointed iris"));
+            assert_eq!(value("replaced"), AnyValue::String("Bristle pointed iris"));
+            assert_eq!(value("joined"), AnyValue::String("setosa:Arctic"));
+            assert_eq!(value("piped"), AnyValue::String("setosa/Arctic"));
+            assert_eq!(value("second_part"), AnyValue::String("pointed iris"));
+            assert_eq!(value("trimmed"), AnyValue::String("setosa"));
+            assert_eq!(value("left_trimmed"), AnyValue::String("a "));
+            assert_eq!(value("right_trimmed"), AnyValue::String("  a"));
+            assert_eq!(value("left_padded"), AnyValue::String("**setosa"));
+            assert_eq!(value("right_padded"), AnyValue::String("set"));
+            assert_eq!(value("left_filled"), AnyValue::String("xyxa"));
+            assert_eq!(value("right_filled"), AnyValue::String("axy"));
+            assert_eq!(value("head"), AnyValue::String("se"));
+            assert_eq!(value("whole"), AnyValue::String("setosa"));
+            assert_eq!(value("masked"), AnyValue::String("Br_stl_-p__nt_d _r_s"));
+            assert_eq!(value("extracted"), AnyValue::String("pointed"));
+        }
+
+        // index 0 of split_part is an error, a computed one fails when collected
+        let sql = format!(
+            "SELECT split_part(common_name, '-', 0) FROM {}",
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(matches!(res, Err(CustomError::SqlExprFuncArgsItem(_))));
+        let sql = format!(
+            "SELECT split_part(common_name, '-', length(species) - 6) FROM {}",
+            resource("species.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(matches!(res, Err(CustomError::PolarsError { .. })));
+    }
+
+    #[tokio::test]
+    async fn date_function_query_work() {
+        let sql = format!(
+            "SELECT location,
+                date_trunc('month', last_updated_date) as month_start,
+                extract(year from last_updated_date) as year,
+                date_part('dow', last_updated_date) as dow,
+                strftime(last_updated_date, '%d/%m/%Y') as formatted,
+                to_date('2024/08/10', '%Y/%m/%d') as parsed,
+                date_add(last_updated_date, INTERVAL '1 month') as next_month,
+                date_sub(last_updated_date, INTERVAL '1 day') as yesterday,
+                date_diff('day', DATE '2024-08-01', last_updated_date) as days,
+                date_diff('month', DATE '2024-01-31', last_updated_date) as months,
+                epoch(DATE '1970-01-02') as seconds,
+                to_timestamp(86400) as epoch_day,
+                current_date >= last_updated_date as past
+            FROM {} where iso_code = 'AFG'
+                and last_updated_date between DATE '2024-08-01' and DATE '2024-08-31'",
+            resource("owid-covid-latest.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            let value = |name: &str| dataset.column(name).unwrap().get(0).unwrap();
+            assert_eq!(value("month_start").to_string(), "2024-08-01 00:00:00");
+            assert_eq!(value("year"), AnyValue::Int32(2024));
+            assert_eq!(value("dow").try_extract::<i32>().unwrap(), 0);
+            assert_eq!(value("formatted"), AnyValue::String("04/08/2024"));
+            assert_eq!(value("parsed").to_string(), "2024-08-10");
+            assert_eq!(value("next_month").to_string(), "2024-09-04 00:00:00");
+            assert_eq!(value("yesterday").to_string(), "2024-08-03 00:00:00");
+            assert_eq!(value("days").try_extract::<i64>().unwrap(), 3);
+            assert_eq!(value("months").try_extract::<i64>().unwrap(), 7);
+            assert_eq!(value("seconds"), AnyValue::Float64(86400.0));
+            assert_eq!(value("epoch_day").to_string(), "1970-01-02 00:00:00");
+            assert_eq!(value("past"), AnyValue::Boolean(true));
+        }
+
+        // iso date string is parsed when compared to date literal
+        let sql = format!(
+            "SELECT location FROM {} where last_updated_date > DATE '2024-08-04'",
+            resource("owid-covid-latest.csv")
+        );
+        let res = query(sql, FormatType::Csv).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 16);
+        }
+    }
+
+    #[tokio::test]
+    async fn math_function_query_work() {
+        let sql = format!(
+            "SELECT abs(-sepalLength) as a,
+                round(sepalLength / 3, 2) as r,
+                ceil(petalLength) as c,
+                floor(sepalWidth) as f,
+                sqrt(power(sepalWidth, 2)) as s,
+                ln(exp(1)) as e,
+                log10(100) as l10,
+                log(2, 8) as l2,
+                sign(-petalWidth) as sg,
+                round(degrees(pi())) as deg,
+                round(sin(radians(90)), 6) as sn,
+                div(-7, 2) as d,
+                mod(7, 3) as m,
+                div(7, -2) as d2,
+                div(-6, 2) as d3,
+                mod(-7, 2) as m2,
+                mod(7, -3) as m3,
+                mod(-7.5, 2) as m4
+            FROM {} where round(sepalLength) = 5 and sqrt(petalWidth) < 0.5 and sepalWidth = 3.5
+            LIMIT 1",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 1);
+            let value = |name: &str| {
+                let value = dataset.column(name).unwrap().get(0).unwrap();
+                value.try_extract::<f64>().unwrap()
+            };
+            assert_eq!(value("a"), 5.1);
+            assert_eq!(value("r"), 1.7);
+            assert_eq!(value("c"), 2.0);
+            assert_eq!(value("f"), 3.0);
+            assert_eq!(value("s"), 3.5);
+            assert!((value("e") - 1.0).abs() < 1e-9);
+            assert_eq!(value("l10"), 2.0);
+            assert!((value("l2") - 3.0).abs() < 1e-9);
+            assert_eq!(value("sg"), -1.0);
+            assert_eq!(value("deg"), 180.0);
+            assert_eq!(value("sn"), 1.0);
+            assert_eq!(value("d"), -3.0);
+            assert_eq!(value("m"), 1.0);
+            assert_eq!(value("d2"), -3.0);
+            assert_eq!(value("d3"), -3.0);
+            assert_eq!(value("m2"), -1.0);
+            assert_eq!(value("m3"), 1.0);
+            assert_eq!(value("m4"), -1.5);
+        }
+
+        // scalar function around and inside aggregation
+        let sql = format!(
+            "SELECT species, round(avg(sepalLength), 1) as average, sum(abs(petalWidth - 1)) as spread
+            FROM {} group by species order by species asc",
+            resource("iris.json")
+        );
+        let res = query(sql, FormatType::Json).await;
+        assert!(res.is_ok());
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 3);
+            let average = dataset.column("average").unwrap().get(0).unwrap();
+            assert_eq!(average.try_extract::<f64>().unwrap(), 5.0);
+            assert!(dataset.column("spread").unwrap().get(0).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn group_by_expression_query_work() {
+        let covid = resource("owid-covid-latest.csv");
+        let iris = resource("iris.json");
+        for (sql, format_type, height, first) in [
+            // ordinal
+            (
+                format!(
+                    "SELECT strftime(last_updated_date, '%d') as day, count(*) as n
+                    FROM {} GROUP BY 1 ORDER BY n DESC",
+                    covid
+                ),
+                FormatType::Csv,
+                6,
+                231,
+            ),
+            // alias
+            (
+                format!(
+                    "SELECT upper(continent) as area, count(*) as n FROM {}
+                    GROUP BY area ORDER BY n DESC",
+                    covid
+                ),
+                FormatType::Csv,
+                7,
+                57,
+            ),
+            // expression, the key is computed once
+            (
+                format!(
+                    "SELECT sepalLength > 5 as long, count(*) as n FROM {}
+                    GROUP BY sepalLength > 5 ORDER BY n ASC",
+                    iris
+                ),
+                FormatType::Json,
+                2,
+                32,
+            ),
+            // expression which is not selected
+            (
+                format!(
+                    "SELECT count(*) as n FROM {} GROUP BY floor(sepalLength) ORDER BY n DESC",
+                    iris
+                ),
+                FormatType::Json,
+                4,
+                61,
+            ),
+        ] {
+            let res = query(&sql, format_type).await;
+            assert!(res.is_ok(), "{}", sql);
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height, "{}", sql);
+                let n = dataset.column("n").unwrap().get(0).unwrap();
+                assert_eq!(n.try_extract::<u32>().unwrap(), first, "{}", sql);
+            }
+        }
+
+        for group_by in ["3", "0", "n", "count(*)", "ROLLUP (species)"] {
+            let sql = format!(
+                "SELECT species, count(*) as n FROM {} GROUP BY {}",
+                iris, group_by
+            );
+            assert!(query(sql, FormatType::Json).await.is_err(), "{}", group_by);
+        }
+    }
+
+    #[tokio::test]
+    async fn order_by_expression_query_work() {
+        let iris = resource("iris.json");
+        for (sql, column, first) in [
+            // ordinal
+            (
+                format!(
+                    "SELECT species, sepalLength * 2 as twice FROM {} ORDER BY 2 DESC LIMIT 1",
+                    iris
+                ),
+                "species",
+                "virginica",
+            ),
+            // alias, ascending by default
+            (
+                format!(
+                    "SELECT species, sepalLength * 2 as twice FROM {} ORDER BY twice LIMIT 1",
+                    iris
+                ),
+                "species",
+                "setosa",
+            ),
+            // expression
+            (
+                format!(
+                    "SELECT species FROM {} ORDER BY petalLength / sepalLength DESC LIMIT 1",
+                    iris
+                ),
+                "species",
+                "virginica",
+            ),
+            // ordinal of wildcard
+            (
+                format!("SELECT * FROM {} ORDER BY 5 DESC, 1 LIMIT 1", iris),
+                "species",
+                "virginica",
+            ),
+        ] {
+            let res = query(&sql, FormatType::Json).await;
+            assert!(res.is_ok(), "{}", sql);
+            if let Ok(dataset) = res {
+                let value = dataset.column(column).unwrap().get(0).unwrap();
+                assert_eq!(value.get_str(), Some(first), "{}", sql);
+            }
+        }
+
+        let covid = resource("owid-covid-latest.csv");
+        for (order_by, first) in [
+            ("continent DESC", None),
+            ("continent DESC NULLS LAST", Some("South America")),
+            ("continent NULLS FIRST", None),
+            ("continent", Some("Africa")),
+        ] {
+            let sql = format!(
+                "SELECT continent FROM {} ORDER BY {} LIMIT 1",
+                covid, order_by
+            );
+            let res = query(&sql, FormatType::Csv).await;
+            assert!(res.is_ok(), "{}", sql);
+            if let Ok(dataset) = res {
+                let value = dataset.column("continent").unwrap().get(0).unwrap();
+                assert_eq!(value.get_str(), first, "{}", sql);
+            }
+        }
+
+        for order_by in ["0", "3", "(SELECT 1)", "ALL"] {
+            let sql = format!(
+                "SELECT species, sepalLength FROM {} ORDER BY {}",
+                iris, order_by
+            );
+            assert!(query(sql, FormatType::Json).await.is_err(), "{}", order_by);
+        }
+    }
+
+    #[tokio::test]
+    async fn qualified_column_query_work() {
+        let sql = format!(
+            "SELECT c.location, c.total_deaths FROM {} AS c
+            WHERE c.total_deaths > 100000 ORDER BY c.total_deaths DESC",
+            resource("owid-covid-latest.csv")
+        );
+        let res = query(&sql, FormatType::Csv).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.get_column_names(), vec!["location", "total_deaths"]);
+            assert!(dataset.height() > 0);
+        }
+
+        // self join, the column of the right side is reached by its alias
+        let iris = resource("iris.json");
+        let sql = format!(
+            "SELECT a.sepalLength, b.sepalLength FROM {} AS a JOIN {} AS b
+            ON a.species = b.species AND a.sepalLength < b.sepalLength",
+            iris, iris
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 3462);
+            assert_eq!(dataset.width(), 2);
+        }
+
+        let sql = format!(
+            "SELECT i.species, s.common_name, count(*) as n FROM {} i
+            JOIN {} s USING (species) GROUP BY i.species, s.common_name ORDER BY s.common_name",
+            iris,
+            resource("species.csv")
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 2);
+            let name = dataset.column("common_name").unwrap().get(0).unwrap();
+            assert_eq!(name.get_str(), Some("Bristle-pointed iris"));
+        }
+
+        // common table expression is qualified by its name
+        let sql = format!(
+            "WITH big AS (SELECT species FROM {} WHERE sepalLength > 7.5)
+            SELECT big.species FROM big",
+            iris
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 6);
+        }
+
+        let sql = format!("SELECT i.length FROM {} AS i", iris);
+        let res = query(&sql, FormatType::Json).await;
+        assert!(
+            matches!(&res, Err(CustomError::SqlColumnError(e)) if e.contains("i.sepalLength")),
+            "{:?}",
+            res
+        );
+
+        // unknown qualifier and unknown column list all the columns
+        for sql in [
+            "SELECT x.species FROM {} AS i",
+            "SELECT nosuch FROM {} AS i",
+            "SELECT species FROM {} AS i WHERE i.nosuch > 1",
+        ] {
+            let sql = sql.replace("{}", &iris);
+            let res = query(&sql, FormatType::Json).await;
+            assert!(
+                matches!(&res, Err(CustomError::SqlColumnError(e))
+                    if e.contains("i.species") && e.contains("sepalWidth")),
+                "{:?}",
+                res
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn quoted_identifier_query_work() {
+        let iris = resource("iris.json");
+        let sql = format!(
+            r#"WITH t AS (SELECT sepalLength AS "Sepal Length", petalLength AS `total length (cm)` FROM {})
+            SELECT "Sepal Length", `total length (cm)` FROM t WHERE "Sepal Length" > 7.5"#,
+            iris
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 6);
+            assert_eq!(
+                dataset.get_column_names(),
+                vec!["Sepal Length", "total length (cm)"]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn case_insensitive_query_work() {
+        let iris = resource("iris.json");
+        let sql = format!(
+            "SELECT SEPALLENGTH, Species FROM {} WHERE sepallength > 7.5 ORDER BY SepalLength",
+            iris
+        );
+        assert!(query(&sql, FormatType::Json).await.is_err());
+        let res = query_with_resolution(&sql, FormatType::Json, Resolution::CaseInsensitive).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 6);
+            assert_eq!(dataset.get_column_names(), vec!["sepalLength", "species"]);
+        }
+
+        let sql = format!(
+            "SELECT I.SPECIES, s.Common_Name, count(*) AS n FROM {} AS i
+            JOIN {} s ON i.Species = S.species GROUP BY I.species, S.COMMON_NAME",
+            iris,
+            resource("species.csv")
+        );
+        let res = query_with_resolution(&sql, FormatType::Json, Resolution::CaseInsensitive).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 2);
+        }
+
+        let sql = format!(
+            r#"WITH t AS (SELECT sepalLength AS "Length", petalLength AS "LENGTH" FROM {})
+            SELECT length FROM t"#,
+            iris
+        );
+        let res = query_with_resolution(&sql, FormatType::Json, Resolution::CaseInsensitive).await;
+        assert!(
+            matches!(res, Err(CustomError::SqlAmbiguousColumnError(_))),
+            "{:?}",
+            res
+        );
+    }
+
+    #[tokio::test]
+    async fn condition_tokenize_query_work() {
+        let sql = format!(
+            "SELECT species FROM {} WHERE species='setosa' AND sepalLength!=5.0 AND petalLength/petalWidth>5",
+            resource("iris.json")
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 29);
+        }
+    }
+
+    #[tokio::test]
+    async fn table_function_query_work() {
+        // sources of different formats in one query
+        let sql = format!(
+            "SELECT i.species, s.column_2 AS common_name, count(*) AS n
+            FROM read_parquet('{}') AS i
+            JOIN read_csv('{}', delimiter => ';', header => false) AS s ON i.species = s.column_1
+            GROUP BY i.species, s.column_2 ORDER BY i.species",
+            resource("iris.parquet"),
+            resource("species-semicolon.csv")
+        );
+        let res = query(&sql, FormatType::Json).await;
+        assert!(res.is_ok(), "{:?}", res);
+        if let Ok(dataset) = res {
+            assert_eq!(dataset.height(), 3);
+            let name = dataset.column("common_name").unwrap().get(2).unwrap();
+            assert_eq!(name.get_str(), Some("Southern blue flag"));
+        }
+
+        let iris = resource("iris.ndjson");
+        for (source, height) in [
+            (format!("read_ndjson('{}')", iris), 150),
+            (
+                format!("read_ndjson({}, infer_schema_length => 10)", iris),
+                150,
+            ),
+            (format!("read_json('{}')", resource("iris.json")), 150),
+            (resource("iris.parquet"), 150),
+            (
+                format!(
+                    "read_csv('{}', delimiter => ';', header => false, skip_rows => 1)",
+                    resource("species-semicolon.csv")
+                ),
+                2,
+            ),
+        ] {
+            let sql = format!("SELECT * FROM {}", source);
+            let res = query(&sql, FormatType::Csv).await;
+            assert!(res.is_ok(), "{:?}", res);
+            if let Ok(dataset) = res {
+                assert_eq!(dataset.height(), height, "{}", sql);
+            }
+        }
+    }
 }
@@ -3,16 +3,28 @@ use crate::DataSet;
 use anyhow::Result;
 use polars::prelude::*;
 use std::io::Cursor;
+use std::num::NonZeroUsize;
 
 pub trait Load {
     type Error;
     fn load(self) -> Result<DataSet, Self::Error>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FormatType {
     Csv,
     Json,
+    NdJson,
+    Parquet,
+}
+
+impl FormatType {
+    /// Detect format type by the file extension of source
+    pub fn from_source(source: &str) -> Option<Self> {
+        let path = source.split(['?', '#']).next().unwrap_or(source);
+        let (_, extension) = path.rsplit_once('.')?;
+        extension.try_into().ok()
+    }
 }
 
 impl TryFrom<&str> for FormatType {
@@ -22,37 +34,61 @@ impl TryFrom<&str> for FormatType {
         match value.to_lowercase().as_str() {
             "csv" => Ok(FormatType::Csv),
             "json" => Ok(FormatType::Json),
+            "ndjson" | "jsonl" => Ok(FormatType::NdJson),
+            "parquet" => Ok(FormatType::Parquet),
             v => Err(CustomError::LoadTypeError(v.to_string())),
         }
     }
 }
 
+/// Read options of source, example: read_csv('a.csv', delimiter => ';', header => false)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    pub(crate) delimiter: Option<u8>,
+    pub(crate) quote: Option<u8>,
+    pub(crate) header: Option<bool>,
+    pub(crate) skip_rows: Option<usize>,
+    pub(crate) infer_schema_length: Option<usize>,
+}
+
 #[derive(Debug)]
 pub enum Loader {
     Csv(CsvLoader),
     Json(JsonLoader),
+    NdJson(NdJsonLoader),
+    Parquet(ParquetLoader),
 }
 
 #[derive(Default, Debug)]
-pub struct CsvLoader(pub(crate) String);
+pub struct CsvLoader(pub(crate) Vec<u8>, pub(crate) LoadOptions);
 
 #[derive(Default, Debug)]
-pub struct JsonLoader(pub(crate) String);
+pub struct JsonLoader(pub(crate) Vec<u8>, pub(crate) LoadOptions);
+
+#[derive(Default, Debug)]
+pub struct NdJsonLoader(pub(crate) Vec<u8>, pub(crate) LoadOptions);
+
+#[derive(Default, Debug)]
+pub struct ParquetLoader(pub(crate) Vec<u8>);
 
 impl Loader {
     pub fn load(self) -> Result<DataSet> {
         match self {
             Loader::Csv(csv) => csv.load(),
             Loader::Json(json) => json.load(),
+            Loader::NdJson(ndjson) => ndjson.load(),
+            Loader::Parquet(parquet) => parquet.load(),
         }
     }
 }
 
-pub fn detect_content(format_type: FormatType, data: String) -> Loader {
+pub fn detect_content(format_type: FormatType, data: Vec<u8>, options: LoadOptions) -> Loader {
     // ToDo Content Detection
     match format_type {
-        FormatType::Csv => Loader::Csv(CsvLoader(data)),
-        FormatType::Json => Loader::Json(JsonLoader(data)),
+        FormatType::Csv => Loader::Csv(CsvLoader(data, options)),
+        FormatType::Json => Loader::Json(JsonLoader(data, options)),
+        FormatType::NdJson => Loader::NdJson(NdJsonLoader(data, options)),
+        FormatType::Parquet => Loader::Parquet(ParquetLoader(data)),
     }
 }
 
@@ -60,7 +96,26 @@ impl Load for CsvLoader {
     type Error = anyhow::Error;
 
     fn load(self) -> Result<DataSet, Self::Error> {
-        let df = CsvReader::new(Cursor::new(self.0)).finish()?;
+        let CsvLoader(data, options) = self;
+        let mut read_options = CsvReadOptions::default()
+            .with_has_header(options.header.unwrap_or(true))
+            .with_skip_rows(options.skip_rows.unwrap_or(0))
+            .map_parse_options(|parse_options| {
+                let parse_options = match options.delimiter {
+                    Some(delimiter) => parse_options.with_separator(delimiter),
+                    None => parse_options,
+                };
+                match options.quote {
+                    Some(quote) => parse_options.with_quote_char(Some(quote)),
+                    None => parse_options,
+                }
+            });
+        if let Some(length) = options.infer_schema_length {
+            read_options = read_options.with_infer_schema_length(Some(length));
+        }
+        let df = read_options
+            .into_reader_with_file_handle(Cursor::new(data))
+            .finish()?;
         Ok(DataSet(df))
     }
 }
@@ -69,7 +124,33 @@ impl Load for JsonLoader {
     type Error = anyhow::Error;
 
     fn load(self) -> Result<DataSet, Self::Error> {
-        let df = JsonReader::new(Cursor::new(self.0)).finish()?;
+        let JsonLoader(data, options) = self;
+        let mut reader = JsonReader::new(Cursor::new(data));
+        if let Some(length) = options.infer_schema_length {
+            reader = reader.infer_schema_len(NonZeroUsize::new(length));
+        }
+        Ok(DataSet(reader.finish()?))
+    }
+}
+
+impl Load for NdJsonLoader {
+    type Error = anyhow::Error;
+
+    fn load(self) -> Result<DataSet, Self::Error> {
+        let NdJsonLoader(data, options) = self;
+        let mut reader = JsonReader::new(Cursor::new(data)).with_json_format(JsonFormat::JsonLines);
+        if let Some(length) = options.infer_schema_length {
+            reader = reader.infer_schema_len(NonZeroUsize::new(length));
+        }
+        Ok(DataSet(reader.finish()?))
+    }
+}
+
+impl Load for ParquetLoader {
+    type Error = anyhow::Error;
+
+    fn load(self) -> Result<DataSet, Self::Error> {
+        let df = ParquetReader::new(Cursor::new(self.0)).finish()?;
         Ok(DataSet(df))
     }
 }
@@ -0,0 +1,749 @@
+use crate::convert::{
+    Cte, Distinct, JoinOn, NullsOrder, OrderType, Resolution, SetOperation, Source, Sql, Subquery,
+    SubqueryKind, resolve_case, resolve_name_case,
+};
+use crate::fetcher::retrieve_data;
+use crate::loader::{FormatType, LoadOptions, detect_content};
+use crate::{CustomError, QueryResult};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Build polars lazy frame from custom Sql struct
+pub(crate) struct Planner {
+    // Format of sources whose extension can not be recognized
+    format_type: FormatType,
+    // Common table expressions visible to the current query
+    ctes: HashMap<String, LazyFrame>,
+    // Resolution of column names
+    resolution: Resolution,
+}
+
+impl Planner {
+    pub(crate) fn new(format_type: FormatType) -> Self {
+        Self {
+            format_type,
+            ctes: HashMap::new(),
+            resolution: Resolution::default(),
+        }
+    }
+
+    pub(crate) fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Convert Sql to lazy frame, every source is fetched and loaded on demand
+    pub(crate) async fn plan(&self, mut sql: Sql<'_>) -> QueryResult<LazyFrame> {
+        if sql.ctes.is_empty() {
+            self.plan_query(sql).await
+        } else {
+            let planner = self.scope(std::mem::take(&mut sql.ctes)).await?;
+            planner.plan_query(sql).await
+        }
+    }
+
+    /// Plan common table expressions in order, each one is visible to the later ones
+    /// and to the main query of returned planner
+    async fn scope(&self, ctes: Vec<Cte<'_>>) -> QueryResult<Planner> {
+        let mut planner = Planner {
+            format_type: self.format_type,
+            ctes: self.ctes.clone(),
+            resolution: self.resolution,
+        };
+        for Cte { name, columns, sql } in ctes {
+            let mut frame = Box::pin(planner.plan(sql)).await?.cache();
+            if !columns.is_empty() {
+                let schema = frame
+                    .collect_schema()
+                    .map_err(|e| CustomError::PolarsError {
+                        error: e.to_string(),
+                    })?;
+                if schema.len() != columns.len() {
+                    return Err(CustomError::SqlCteError(format!(
+                        "{} has {} columns but {} column names",
+                        name,
+                        schema.len(),
+                        columns.len()
+                    )));
+                }
+                let existing = schema.iter_names().cloned().collect::<Vec<PlSmallStr>>();
+                frame = frame.rename(existing, columns, true);
+            }
+            planner.ctes.insert(name.to_string(), frame);
+        }
+
+        Ok(planner)
+    }
+
+    /// Plan query whose common table expressions are already in scope
+    async fn plan_query(&self, mut sql: Sql<'_>) -> QueryResult<LazyFrame> {
+        let (mut frame, columns) = self.relation(&sql.source).await?;
+        let schema = frame
+            .collect_schema()
+            .map_err(|e| CustomError::PolarsError {
+                error: e.to_string(),
+            })?;
+        let names = match self.resolution {
+            Resolution::CaseSensitive => Vec::new(),
+            Resolution::CaseInsensitive => {
+                let names = schema
+                    .iter_names()
+                    .cloned()
+                    .chain(columns.names())
+                    .collect::<Vec<PlSmallStr>>();
+                sql.resolve_case(&names)?;
+                names
+            }
+        };
+
+        let Sql {
+            condition,
+            having,
+            selection,
+            aggregation,
+            window,
+            offset,
+            limit,
+            distinct,
+            order_by,
+            group_by,
+            subqueries,
+            ..
+        } = sql;
+
+        // columns named by the query itself, aliases and placeholders of subqueries
+        let aliases = selection
+            .iter()
+            .chain(aggregation.iter())
+            .chain(window.iter())
+            .chain(group_by.iter())
+            .flat_map(|expr| expr.into_iter())
+            .filter_map(|e| match e {
+                Expr::Alias(_, name) => Some(name.clone()),
+                _ => None,
+            })
+            .chain(subqueries.iter().map(|v| PlSmallStr::from(v.name.as_str())))
+            .collect::<Vec<PlSmallStr>>();
+        let exprs = condition
+            .iter()
+            .chain(having.iter())
+            .chain(selection.iter())
+            .chain(aggregation.iter())
+            .chain(window.iter())
+            .chain(group_by.iter())
+            .chain(order_by.iter().map(|(expr, ..)| expr));
+        for expr in exprs {
+            columns.check(expr, &schema, &aliases)?;
+        }
+
+        let mut resolved = self.subqueries(subqueries).await?;
+        resolved.extend(columns.resolved());
+        let condition = condition.map(|expr| resolve(expr, &resolved));
+        let having = having.map(|expr| resolve(expr, &resolved));
+        let selection = resolve_list(selection, &resolved);
+        let aggregation = resolve_list(aggregation, &resolved);
+        let window = resolve_list(window, &resolved);
+        let group_by = resolve_list(group_by, &resolved);
+
+        let filtered = match condition {
+            Some(expr) => frame.filter(expr),
+            None => frame,
+        };
+
+        let mut frame = if !group_by.is_empty() {
+            // group by select
+            let grouped = filtered.group_by(group_by).agg(aggregation);
+            match having {
+                Some(expr) => grouped.filter(expr),
+                None => grouped,
+            }
+        } else if !aggregation.is_empty() {
+            // global aggregation select, a single row of the whole table
+            let aggregated = filtered.select(aggregation);
+            match having {
+                Some(expr) => aggregated.filter(expr),
+                None => aggregated,
+            }
+        } else {
+            // general select
+            filtered
+        };
+
+        // window functions are computed before the order and limit
+        if !window.is_empty() {
+            frame = frame.with_columns(window);
+        }
+
+        // order by and limit are applied before the projection, so that the keys
+        // can be source columns, group keys or hidden aggregations
+        if !order_by.is_empty() {
+            let mut exprs = Vec::new();
+            let mut descending = Vec::new();
+            let mut nulls_last = Vec::new();
+            for (expr, order_type, nulls_order) in order_by {
+                exprs.push(resolve(expr, &resolved));
+                descending.push(order_type == OrderType::Desc);
+                nulls_last.push(nulls_order == NullsOrder::Last);
+            }
+            frame = frame.sort_by_exprs(
+                exprs,
+                SortMultipleOptions::default()
+                    .with_order_descending_multi(descending)
+                    .with_nulls_last_multi(nulls_last),
+            );
+        }
+
+        let slice = |frame: LazyFrame| {
+            if offset.is_some() || limit.is_some() {
+                frame.slice(offset.unwrap_or(0), limit.unwrap_or(20) as IdxSize)
+            } else {
+                frame
+            }
+        };
+
+        // distinct keeps the first row of sorted frame, limit is applied to distinct rows
+        let frame = match distinct {
+            Some(Distinct::All) => slice(
+                frame
+                    .select(selection)
+                    .unique_stable(None, UniqueKeepStrategy::First),
+            ),
+            Some(Distinct::On(columns)) => {
+                let subset = columns
+                    .into_iter()
+                    .map(|name| {
+                        let name = resolve_name_case(name, &names)?;
+                        Ok(match resolved.get(name.as_str()) {
+                            Some(Expr::Column(column)) => column.clone(),
+                            _ => name,
+                        })
+                    })
+                    .collect::<QueryResult<Vec<PlSmallStr>>>()?;
+                slice(frame.unique_stable(Some(subset), UniqueKeepStrategy::First))
+                    .select(selection)
+            }
+            None => slice(frame).select(selection),
+        };
+
+        Ok(frame)
+    }
+
+    /// Load source relation to lazy frame, joined relations are combined by polars lazy join
+    pub(crate) async fn source(&self, source: &Source<'_>) -> QueryResult<LazyFrame> {
+        Ok(self.relation(source).await?.0)
+    }
+
+    /// Load source relation to lazy frame with its qualified columns, a table is qualified
+    /// by its name and a relation with alias by the alias
+    async fn relation(&self, source: &Source<'_>) -> QueryResult<(LazyFrame, Qualified)> {
+        match source {
+            Source::Table(name) => {
+                let mut frame = if self.ctes.contains_key(*name) {
+                    self.ctes[*name].clone()
+                } else {
+                    let format_type = FormatType::from_source(name).unwrap_or(self.format_type);
+                    load(name, format_type, LoadOptions::default()).await?
+                };
+                let columns = Qualified::new(name, &mut frame)?;
+                Ok((frame, columns))
+            }
+            Source::Read {
+                path,
+                format_type,
+                options,
+            } => {
+                let mut frame = load(path, *format_type, options.clone()).await?;
+                let columns = Qualified::new(path, &mut frame)?;
+                Ok((frame, columns))
+            }
+            Source::Query(sql) => Ok((
+                Box::pin(self.plan(sql.as_ref().clone())).await?,
+                Qualified::default(),
+            )),
+            Source::Alias(source, alias) => {
+                let (mut frame, _) = Box::pin(self.relation(source)).await?;
+                let columns = Qualified::new(alias, &mut frame)?;
+                Ok((frame, columns))
+            }
+            Source::Set {
+                left,
+                right,
+                op,
+                all,
+            } => {
+                let left = Box::pin(self.plan(left.as_ref().clone())).await?;
+                let right = Box::pin(self.plan(right.as_ref().clone())).await?;
+                Ok((set_operation(left, right, *op, *all)?, Qualified::default()))
+            }
+            Source::Join {
+                left,
+                right,
+                how,
+                on,
+            } => {
+                let left = Box::pin(self.relation(left)).await?;
+                let right = Box::pin(self.relation(right)).await?;
+                join(left, right, how, on, self.resolution)
+            }
+        }
+    }
+
+    /// Execute subqueries of expressions, every placeholder column is mapped to the result
+    async fn subqueries(
+        &self,
+        subqueries: Vec<Subquery<'_>>,
+    ) -> QueryResult<HashMap<String, Expr>> {
+        let mut resolved = HashMap::new();
+        for Subquery { name, kind, sql } in subqueries {
+            let mut frame = Box::pin(self.plan(sql)).await?;
+            if kind == SubqueryKind::Exists {
+                frame = frame.limit(1);
+            }
+            let df = frame.collect().map_err(|e| CustomError::PolarsError {
+                error: e.to_string(),
+            })?;
+
+            let expr = match (kind, df.get_columns()) {
+                (SubqueryKind::Exists, _) => lit(df.height() > 0),
+                (SubqueryKind::Scalar, [column]) if df.height() <= 1 => {
+                    lit(column.as_materialized_series().clone()).first()
+                }
+                (SubqueryKind::Scalar, [_]) => {
+                    return Err(CustomError::SqlSubqueryError(format!(
+                        "scalar subquery returns {} rows",
+                        df.height()
+                    )));
+                }
+                (SubqueryKind::List, [column]) => {
+                    lit(column.as_materialized_series().clone()).implode()
+                }
+                (kind, columns) => {
+                    return Err(CustomError::SqlSubqueryError(format!(
+                        "{:?} subquery returns {} columns",
+                        kind,
+                        columns.len()
+                    )));
+                }
+            };
+            resolved.insert(name, expr);
+        }
+
+        Ok(resolved)
+    }
+}
+
+/// Fetch and load source of the format to lazy frame
+async fn load(path: &str, format_type: FormatType, options: LoadOptions) -> QueryResult<LazyFrame> {
+    let ds = detect_content(format_type, retrieve_data(path).await?, options)
+        .load()
+        .map_err(|e| CustomError::FetchError {
+            url: path.to_string(),
+            error: e.to_string(),
+        })?;
+    Ok(ds.0.lazy())
+}
+
+/// Columns of relation reachable by qualifier, each one is (qualifier, column, column of
+/// the frame), example: `c.total_deaths` of `from t as c` is the column total_deaths
+#[derive(Debug, Clone, Default)]
+struct Qualified(Vec<(String, PlSmallStr, PlSmallStr)>);
+
+impl Qualified {
+    fn new(qualifier: &str, frame: &mut LazyFrame) -> QueryResult<Self> {
+        let schema = frame
+            .collect_schema()
+            .map_err(|e| CustomError::PolarsError {
+                error: e.to_string(),
+            })?;
+        Ok(Self(
+            schema
+                .iter_names()
+                .map(|name| (qualifier.to_string(), name.clone(), name.clone()))
+                .collect(),
+        ))
+    }
+
+    fn names(&self) -> impl Iterator<Item = PlSmallStr> + '_ {
+        self.0
+            .iter()
+            .map(|(qualifier, column, _)| format!("{}.{}", qualifier, column).into())
+    }
+
+    /// Qualified names mapped to the columns of the frame
+    fn resolved(&self) -> HashMap<String, Expr> {
+        self.0
+            .iter()
+            .map(|(qualifier, column, name)| {
+                (format!("{}.{}", qualifier, column), col(name.clone()))
+            })
+            .collect()
+    }
+
+    /// Every column used by the expression must be a qualified column, a column of the
+    /// frame or an alias, the error lists the available columns of a known qualifier or
+    /// all the columns otherwise
+    fn check(&self, expr: &Expr, schema: &Schema, aliases: &[PlSmallStr]) -> QueryResult<()> {
+        for e in expr.into_iter() {
+            let Expr::Column(name) = e else {
+                continue;
+            };
+            if schema.contains(name)
+                || aliases.contains(name)
+                || self.names().any(|qualified| qualified == *name)
+            {
+                continue;
+            }
+            let mut available = match name.rsplit_once('.') {
+                Some((qualifier, _)) => self
+                    .names()
+                    .filter(|qualified| {
+                        qualified
+                            .strip_prefix(qualifier)
+                            .is_some_and(|v| v.starts_with('.'))
+                    })
+                    .map(|qualified| qualified.to_string())
+                    .collect::<Vec<String>>(),
+                None => Vec::new(),
+            };
+            if available.is_empty() {
+                available = self
+                    .names()
+                    .chain(schema.iter_names().cloned())
+                    .map(|name| name.to_string())
+                    .collect();
+            }
+            return Err(CustomError::SqlColumnError(format!(
+                "{}, available columns are {}",
+                name,
+                available.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace placeholder columns of subqueries with their results and qualified columns
+/// with the columns of the frame
+fn resolve(expr: Expr, resolved: &HashMap<String, Expr>) -> Expr {
+    if resolved.is_empty() {
+        return expr;
+    }
+    expr.map_expr(|e| match e {
+        Expr::Column(name) => match resolved.get(name.as_str()) {
+            Some(value) => value.clone(),
+            None => Expr::Column(name),
+        },
+        e => e,
+    })
+}
+
+fn resolve_list(list: Vec<Expr>, resolved: &HashMap<String, Expr>) -> Vec<Expr> {
+    list.into_iter()
+        .map(|expr| resolve(expr, resolved))
+        .collect()
+}
+
+/// Combine the frames of two queries by column position, the result is named after
+/// the left query and numeric columns are cast to their common type
+fn set_operation(
+    mut left: LazyFrame,
+    mut right: LazyFrame,
+    op: SetOperation,
+    all: bool,
+) -> QueryResult<LazyFrame> {
+    let left_schema = left
+        .collect_schema()
+        .map_err(|e| CustomError::PolarsError {
+            error: e.to_string(),
+        })?;
+    let right_schema = right
+        .collect_schema()
+        .map_err(|e| CustomError::PolarsError {
+            error: e.to_string(),
+        })?;
+    if left_schema.len() != right_schema.len() {
+        return Err(CustomError::SqlSetOperationError(format!(
+            "{} of {} columns and {} columns",
+            op,
+            left_schema.len(),
+            right_schema.len()
+        )));
+    }
+
+    let (mut left_columns, mut right_columns) = (Vec::new(), Vec::new());
+    for ((name, left_dtype), (right_name, right_dtype)) in
+        left_schema.iter().zip(right_schema.iter())
+    {
+        let dtype = if left_dtype == right_dtype || right_dtype.is_null() {
+            left_dtype.clone()
+        } else if left_dtype.is_null() {
+            right_dtype.clone()
+        } else if left_dtype.is_primitive_numeric() && right_dtype.is_primitive_numeric() {
+            if left_dtype.is_float() || right_dtype.is_float() {
+                DataType::Float64
+            } else {
+                DataType::Int64
+            }
+        } else {
+            return Err(CustomError::SqlSetOperationError(format!(
+                "{} of column {} ({}) and column {} ({})",
+                op, name, left_dtype, right_name, right_dtype
+            )));
+        };
+        left_columns.push(col(name.clone()).cast(dtype.clone()));
+        right_columns.push(col(right_name.clone()).cast(dtype).alias(name.clone()));
+    }
+    let left = left.select(left_columns);
+    let right = right.select(right_columns);
+
+    let frame = match op {
+        SetOperation::Union => {
+            let frame = concat([left, right], UnionArgs::default()).map_err(|e| {
+                CustomError::PolarsError {
+                    error: e.to_string(),
+                }
+            })?;
+            if all {
+                frame
+            } else {
+                frame.unique_stable(None, UniqueKeepStrategy::Any)
+            }
+        }
+        SetOperation::Intersect | SetOperation::Except => {
+            let keys = left_schema
+                .iter_names()
+                .map(|name| col(name.clone()))
+                .collect::<Vec<Expr>>();
+            let how = if op == SetOperation::Intersect {
+                JoinType::Semi
+            } else {
+                JoinType::Anti
+            };
+            left.join_builder()
+                .with(right)
+                .on(keys)
+                .how(how)
+                .join_nulls(true)
+                .finish()
+                .unique_stable(None, UniqueKeepStrategy::Any)
+        }
+    };
+
+    Ok(frame)
+}
+
+/// Join two lazy frames, equal conditions of `on` become join keys and the rest
+/// is used to filter the joined frame, columns of the right side taken by the left side
+/// are suffixed by polars. A condition of outer join can only refer to the side not
+/// preserved, it filters the side before the join
+fn join(
+    (mut left, left_columns): (LazyFrame, Qualified),
+    (mut right, right_columns): (LazyFrame, Qualified),
+    how: &JoinType,
+    on: &JoinOn<'_>,
+    resolution: Resolution,
+) -> QueryResult<(LazyFrame, Qualified)> {
+    let left_schema = left
+        .collect_schema()
+        .map_err(|e| CustomError::PolarsError {
+            error: e.to_string(),
+        })?;
+    let right_schema = right
+        .collect_schema()
+        .map_err(|e| CustomError::PolarsError {
+            error: e.to_string(),
+        })?;
+    // keys merged into the left ones
+    let coalesced = match on {
+        JoinOn::Using(columns) => columns.iter().map(|name| PlSmallStr::from(*name)).collect(),
+        JoinOn::Natural => left_schema
+            .iter_names()
+            .filter(|name| right_schema.contains(name))
+            .cloned()
+            .collect(),
+        JoinOn::Expr(_) | JoinOn::None => Vec::new(),
+    };
+    let mut columns = left_columns.clone();
+    for (qualifier, column, name) in &right_columns.0 {
+        let name = if left_schema.contains(name) && !coalesced.contains(name) {
+            PlSmallStr::from(format!("{}_right", name))
+        } else {
+            name.clone()
+        };
+        columns.0.push((qualifier.clone(), column.clone(), name));
+    }
+
+    let frame = match on {
+        JoinOn::Using(_) | JoinOn::Natural => {
+            let keys = coalesced.into_iter().map(col).collect::<Vec<Expr>>();
+            let how = if keys.is_empty() {
+                JoinType::Cross
+            } else {
+                how.clone()
+            };
+            left.join_builder()
+                .with(right)
+                .on(keys)
+                .how(how)
+                .coalesce(JoinCoalesce::CoalesceColumns)
+                .finish()
+        }
+        JoinOn::None => left
+            .join_builder()
+            .with(right)
+            .how(JoinType::Cross)
+            .finish(),
+        JoinOn::Expr(expr) => {
+            let expr = match resolution {
+                Resolution::CaseSensitive => expr.clone(),
+                Resolution::CaseInsensitive => {
+                    let names = left_schema
+                        .iter_names()
+                        .chain(right_schema.iter_names())
+                        .cloned()
+                        .chain(columns.names())
+                        .collect::<Vec<PlSmallStr>>();
+                    resolve_case(expr.clone(), &names)?
+                }
+            };
+            let mut schema = left_schema.as_ref().clone();
+            schema.merge(right_schema.as_ref().clone());
+            columns.check(&expr, &schema, &[])?;
+            let mut conditions = Vec::new();
+            conjunction(&expr, &mut conditions);
+
+            let left_side = (left_schema.as_ref(), &left_columns.resolved());
+            let right_side = (right_schema.as_ref(), &right_columns.resolved());
+            let (mut left_on, mut right_on, mut residual) = (Vec::new(), Vec::new(), Vec::new());
+            let (mut left_filter, mut right_filter) = (Vec::new(), Vec::new());
+            for condition in conditions {
+                match join_keys(&condition, left_side, right_side)? {
+                    Some((left_key, right_key)) => {
+                        left_on.push(left_key);
+                        right_on.push(right_key);
+                    }
+                    None if *how == JoinType::Inner => {
+                        residual.push(resolve(condition, &columns.resolved()))
+                    }
+                    None if *how == JoinType::Left && within(&condition, right_side, left_side) => {
+                        right_filter.push(resolve(condition, right_side.1))
+                    }
+                    None if *how == JoinType::Right
+                        && within(&condition, left_side, right_side) =>
+                    {
+                        left_filter.push(resolve(condition, left_side.1))
+                    }
+                    None => {
+                        return Err(CustomError::SqlJoinError(format!(
+                            "{} join only supports equal conditions between both sides and \
+                             conditions of the side not preserved, get {}",
+                            how, condition
+                        )));
+                    }
+                }
+            }
+            if let Some(predicate) = left_filter.into_iter().reduce(|acc, item| acc.and(item)) {
+                left = left.filter(predicate);
+            }
+            if let Some(predicate) = right_filter.into_iter().reduce(|acc, item| acc.and(item)) {
+                right = right.filter(predicate);
+            }
+
+            let builder = left.join_builder().with(right);
+            let frame = if left_on.is_empty() {
+                builder.how(JoinType::Cross).finish()
+            } else {
+                builder
+                    .left_on(left_on)
+                    .right_on(right_on)
+                    .how(how.clone())
+                    .coalesce(JoinCoalesce::KeepColumns)
+                    .finish()
+            };
+            match residual.into_iter().reduce(|acc, item| acc.and(item)) {
+                Some(predicate) => frame.filter(predicate),
+                None => frame,
+            }
+        }
+    };
+
+    Ok((frame, columns))
+}
+
+/// Split condition joined by `and` into list
+fn conjunction(expr: &Expr, list: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        } => {
+            conjunction(left, list);
+            conjunction(right, list);
+        }
+        v => list.push(v.clone()),
+    }
+}
+
+/// Whether every column of the condition belongs to the side, an unqualified column of
+/// both sides belongs to neither of them
+fn within(
+    condition: &Expr,
+    (schema, resolved): (&Schema, &HashMap<String, Expr>),
+    (other_schema, other_resolved): (&Schema, &HashMap<String, Expr>),
+) -> bool {
+    condition.into_iter().all(|e| match e {
+        Expr::Column(name) => {
+            resolved.contains_key(name.as_str())
+                || (schema.contains(name)
+                    && !other_resolved.contains_key(name.as_str())
+                    && !other_schema.contains(name))
+        }
+        _ => true,
+    })
+}
+
+/// Extract (left key, right key) from condition like `a = b`, each column is matched
+/// against the qualified columns and the schema of both sides, an unqualified column
+/// of both sides is ambiguous
+fn join_keys(
+    condition: &Expr,
+    left: (&Schema, &HashMap<String, Expr>),
+    right: (&Schema, &HashMap<String, Expr>),
+) -> QueryResult<Option<(Expr, Expr)>> {
+    let Expr::BinaryExpr {
+        left: first,
+        op: Operator::Eq,
+        right: second,
+    } = condition
+    else {
+        return Ok(None);
+    };
+    let (Expr::Column(first), Expr::Column(second)) = (first.as_ref(), second.as_ref()) else {
+        return Ok(None);
+    };
+    for name in [first, second] {
+        if !left.1.contains_key(name.as_str())
+            && !right.1.contains_key(name.as_str())
+            && left.0.contains(name)
+            && right.0.contains(name)
+        {
+            return Err(CustomError::SqlAmbiguousColumnError(name.to_string()));
+        }
+    }
+
+    let key =
+        |name: &PlSmallStr, (schema, resolved): (&Schema, &HashMap<String, Expr>)| match resolved
+            .get(name.as_str())
+        {
+            Some(expr) => Some(expr.clone()),
+            None if schema.contains(name) => Some(col(name.clone())),
+            None => None,
+        };
+
+    Ok(match (key(first, left), key(second, right)) {
+        (Some(left_key), Some(right_key)) => Some((left_key, right_key)),
+        _ => key(second, left).zip(key(first, right)),
+    })
+}